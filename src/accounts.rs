@@ -0,0 +1,223 @@
+use std::{collections::HashMap, thread, time::Duration};
+
+use chrono::Local;
+use crossbeam::{
+    channel::{tick, unbounded, Receiver, RecvError, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+
+enum AccountWatcherMessage {
+    Active(bool),
+}
+
+/// Fairshare and usage of one of the user's accounts, from sshare and sreport.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountUsage {
+    pub account: String,
+    // the user's association within the account
+    pub fairshare: Option<f64>,
+    pub norm_shares: Option<f64>,
+    pub raw_usage: f64,
+    pub effective_usage: Option<f64>,
+    // the account as a whole
+    pub account_raw_usage: f64,
+    // GrpTRESMins cpu limit of the account and the (decayed) usage counted against it
+    pub cpu_minutes_limit: Option<f64>,
+    pub cpu_minutes_used: Option<f64>,
+    // sreport cpu hours since the start of the month
+    pub month_hours: f64,
+    pub user_month_hours: f64,
+}
+
+impl AccountUsage {
+    /// CPU hours left before the account's GrpTRESMins limit, if it has one.
+    pub fn remaining_hours(&self) -> Option<f64> {
+        let limit = self.cpu_minutes_limit?;
+        Some((limit - self.cpu_minutes_used.unwrap_or(0.)).max(0.) / 60.)
+    }
+}
+
+// pull the cpu count out of a TRES string, e.g. "cpu=100000,mem=2000000,gres/gpu=5000"
+fn tres_cpu(tres: &str) -> Option<f64> {
+    tres.split(',')
+        .find_map(|t| t.strip_prefix("cpu="))
+        .and_then(|cpu| cpu.parse().ok())
+}
+
+struct AccountWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<AccountWatcherMessage>,
+    interval: Duration,
+    user: String,
+    active: bool,
+}
+
+impl AccountWatcher {
+    fn new(
+        app: Sender<AppMessage>,
+        receiver: Receiver<AccountWatcherMessage>,
+        interval: Duration,
+        user: String,
+    ) -> Self {
+        Self {
+            app,
+            receiver,
+            interval,
+            user,
+            active: false,
+        }
+    }
+
+    fn run(&mut self) -> Result<(), RecvError> {
+        let ticker = tick(self.interval);
+        loop {
+            select! {
+                recv(self.receiver) -> msg => {
+                    match msg? {
+                        AccountWatcherMessage::Active(active) => {
+                            self.active = active;
+                            self.refresh();
+                        }
+                    }
+                }
+                recv(ticker) -> _ => self.refresh(),
+            }
+        }
+    }
+
+    fn run_command(cmd: &str) -> String {
+        let output = std::process::Command::new("bash")
+            .arg("-c")
+            .arg(cmd)
+            .output()
+            .expect("failed to execute process");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    fn refresh(&self) {
+        // sreport can take a while on a big cluster, only run it while the panel is open
+        if !self.active {
+            return;
+        }
+        let user = match self.user.as_str() {
+            "" => "$USER",
+            user => user,
+        };
+        let format = "--format=Account,User,NormShares,RawUsage,EffectvUsage,FairShare,\
+            GrpTRESMins,GrpTRESRaw";
+
+        // the user's own association in each of their accounts
+        let mut accounts: Vec<AccountUsage> = Vec::new();
+        let output = Self::run_command(&format!(
+            "sshare --noheader --parsable2 -U -u {} {}",
+            user, format
+        ));
+        for line in output.lines() {
+            let parts = line.split('|').map(|s| s.trim()).collect::<Vec<&str>>();
+            if parts.len() < 8 || accounts.iter().any(|a| a.account == parts[0]) {
+                continue;
+            }
+            accounts.push(AccountUsage {
+                account: parts[0].to_string(),
+                norm_shares: parts[2].parse().ok(),
+                raw_usage: parts[3].parse().unwrap_or(0.),
+                effective_usage: parts[4].parse().ok(),
+                fairshare: parts[5].parse().ok(),
+                ..Default::default()
+            });
+        }
+        if accounts.is_empty() {
+            self.app.send(AppMessage::AccountUsage(accounts)).unwrap();
+            return;
+        }
+        let names = accounts
+            .iter()
+            .map(|a| a.account.clone())
+            .collect::<Vec<String>>()
+            .join(",");
+
+        // the rows without a user are the accounts themselves, which is where limits are set
+        let output = Self::run_command(&format!(
+            "sshare --noheader --parsable2 -A {} {}",
+            names, format
+        ));
+        for line in output.lines() {
+            let parts = line.split('|').map(|s| s.trim()).collect::<Vec<&str>>();
+            if parts.len() < 8 || !parts[1].is_empty() {
+                continue;
+            }
+            if let Some(account) = accounts.iter_mut().find(|a| a.account == parts[0]) {
+                account.account_raw_usage = parts[3].parse().unwrap_or(0.);
+                account.cpu_minutes_limit = tres_cpu(parts[6]);
+                account.cpu_minutes_used = tres_cpu(parts[7]);
+            }
+        }
+
+        // cpu hours used this month, by the whole account and by the user
+        let month_start = Local::now().format("%Y-%m-01T00:00:00");
+        let output = Self::run_command(&format!(
+            "sreport --noheader --parsable2 -t Hours cluster AccountUtilizationByUser \
+            Accounts={} Start={} End=now format=Account,Login,Used",
+            names, month_start
+        ));
+        let login = match self.user.as_str() {
+            "" => std::env::var("USER").unwrap_or_default(),
+            user => user.to_string(),
+        };
+        // sreport lists each cluster separately, add them up
+        let mut hours: HashMap<(String, bool), f64> = HashMap::new();
+        for line in output.lines() {
+            let parts = line.split('|').map(|s| s.trim()).collect::<Vec<&str>>();
+            if parts.len() < 3 {
+                continue;
+            }
+            let used: f64 = parts[2].parse().unwrap_or(0.);
+            match parts[1] {
+                "" => *hours.entry((parts[0].to_string(), false)).or_default() += used,
+                l if l == login => *hours.entry((parts[0].to_string(), true)).or_default() += used,
+                _ => {}
+            }
+        }
+        for account in accounts.iter_mut() {
+            let get = |user: bool| {
+                hours
+                    .get(&(account.account.clone(), user))
+                    .copied()
+                    .unwrap_or(0.)
+            };
+            account.month_hours = get(false);
+            account.user_month_hours = get(true);
+        }
+        self.app.send(AppMessage::AccountUsage(accounts)).unwrap();
+    }
+}
+
+#[derive(Debug)]
+pub struct AccountWatcherHandle {
+    sender: Sender<AccountWatcherMessage>,
+    active: bool,
+}
+
+impl AccountWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, interval: Duration, user: String) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = AccountWatcher::new(app, receiver, interval, user);
+        thread::spawn(move || actor.run());
+        Self {
+            sender,
+            active: false,
+        }
+    }
+
+    /// Poll sshare and sreport only while the accounts view is shown.
+    pub fn set_active(&mut self, active: bool) {
+        if self.active != active {
+            self.active = active;
+            self.sender
+                .send(AccountWatcherMessage::Active(active))
+                .unwrap();
+        }
+    }
+}
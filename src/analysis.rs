@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use crate::slurm::{compress_hostlist, expand_hostlist, SlurmJob};
+
+// a node needs at least this many failures before it is flagged, one bad job proves nothing
+const MIN_FLAGGED_FAILURES: usize = 2;
+// how many standard deviations above the expected failures a node has to be to get flagged
+const FLAG_Z_SCORE: f64 = 2.0;
+
+/// How the finished jobs that ran on a node ended.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeFailures {
+    pub node: String,
+    pub jobs: usize,
+    pub failed: usize,
+    pub node_fail: usize,
+    // failed well beyond what the user's overall failure rate predicts
+    pub abnormal: bool,
+}
+
+impl NodeFailures {
+    pub fn failures(&self) -> usize {
+        self.failed + self.node_fail
+    }
+
+    pub fn failure_rate(&self) -> f64 {
+        match self.jobs {
+            0 => 0.,
+            jobs => self.failures() as f64 / jobs as f64,
+        }
+    }
+}
+
+/// Rank the nodes the finished jobs ran on by how often a job on them ended F or NF, worst
+/// first.  A node is flagged abnormal when its failure count is an outlier given the failure
+/// rate over all nodes, so that a broken script failing everywhere doesn't flag every node.
+pub fn node_failures<'a>(jobs: impl IntoIterator<Item = &'a SlurmJob>) -> Vec<NodeFailures> {
+    let mut nodes: HashMap<String, NodeFailures> = HashMap::new();
+    for job in jobs.into_iter().filter(|j| j.is_finished()) {
+        for node in expand_hostlist(&job.node_list) {
+            let entry = nodes.entry(node.clone()).or_insert_with(|| NodeFailures {
+                node,
+                ..Default::default()
+            });
+            entry.jobs += 1;
+            match job.state.as_str() {
+                "F" => entry.failed += 1,
+                "NF" => entry.node_fail += 1,
+                _ => {}
+            }
+        }
+    }
+
+    let total: usize = nodes.values().map(|n| n.jobs).sum();
+    let failures: usize = nodes.values().map(|n| n.failures()).sum();
+    for node in nodes.values_mut() {
+        // failure rate everywhere else, so a bad node doesn't raise the bar it is measured by
+        let others = total - node.jobs;
+        let rate = (failures - node.failures()) as f64 / others as f64;
+        // z-score of the node's failures against a binomial with that rate
+        let expected = node.jobs as f64 * rate;
+        let deviation = (node.jobs as f64 * rate * (1. - rate)).sqrt();
+        let z = match (others, deviation > 0.) {
+            // with nothing to compare against there is no telling the node from the jobs
+            (0, _) => 0.,
+            (_, true) => (node.failures() as f64 - expected) / deviation,
+            // nothing failed anywhere else, so any repeated failure here stands out
+            (_, false) => f64::INFINITY,
+        };
+        // a node failure is slurm blaming the node itself, so always worth flagging
+        node.abnormal =
+            node.node_fail > 0 || (node.failures() >= MIN_FLAGGED_FAILURES && z > FLAG_Z_SCORE);
+    }
+
+    let mut nodes: Vec<NodeFailures> = nodes.into_values().collect();
+    nodes.sort_by(|a, b| {
+        b.abnormal
+            .cmp(&a.abnormal)
+            .then(b.failure_rate().total_cmp(&a.failure_rate()))
+            .then(b.failures().cmp(&a.failures()))
+            .then(a.node.cmp(&b.node))
+    });
+    nodes
+}
+
+/// sbatch option keeping resubmitted jobs off the flagged nodes, e.g. "--exclude=node[012,020]".
+pub fn exclude_option(nodes: &[NodeFailures]) -> Option<String> {
+    let flagged: Vec<String> = nodes
+        .iter()
+        .filter(|n| n.abnormal)
+        .map(|n| n.node.clone())
+        .collect();
+    match flagged.is_empty() {
+        true => None,
+        false => Some(format!("--exclude={}", compress_hostlist(&flagged))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jobs(node_list: &str, state: &str, count: usize) -> Vec<SlurmJob> {
+        (0..count)
+            .map(|i| SlurmJob {
+                job_id: format!("{}{}{}", node_list, state, i),
+                state: state.to_string(),
+                node_list: node_list.to_string(),
+                ..SlurmJob::default()
+            })
+            .collect()
+    }
+
+    fn find<'a>(nodes: &'a [NodeFailures], node: &str) -> &'a NodeFailures {
+        nodes.iter().find(|n| n.node == node).unwrap()
+    }
+
+    #[test]
+    fn outliers_are_flagged() {
+        let mut all = jobs("node[01-04]", "CD", 40);
+        all.extend(jobs("node[01-04]", "F", 2));
+        all.extend(jobs("node05", "F", 6));
+        all.extend(jobs("node05", "CD", 4));
+        all.extend(jobs("node06", "R", 3));
+        let nodes = node_failures(&all);
+        assert_eq!(nodes.len(), 5);
+        assert_eq!(nodes[0].node, "node05");
+        assert!(nodes[0].abnormal);
+        assert_eq!((nodes[0].jobs, nodes[0].failed), (10, 6));
+        assert!(!find(&nodes, "node01").abnormal);
+        assert_eq!(exclude_option(&nodes).as_deref(), Some("--exclude=node05"));
+    }
+
+    #[test]
+    fn failing_everywhere_flags_nothing() {
+        let mut all = jobs("node[01-03]", "F", 5);
+        all.extend(jobs("node[01-03]", "CD", 5));
+        let nodes = node_failures(&all);
+        assert!(nodes.iter().all(|n| !n.abnormal));
+        assert_eq!(exclude_option(&nodes), None);
+    }
+
+    #[test]
+    fn a_single_node_is_not_compared() {
+        // with no other node there is no failure rate to measure against
+        let nodes = node_failures(&jobs("node01", "F", 5));
+        assert_eq!(nodes.len(), 1);
+        assert!(!nodes[0].abnormal);
+    }
+
+    #[test]
+    fn repeated_failures_stand_out_where_nothing_else_failed() {
+        let mut all = jobs("node[01-02]", "CD", 5);
+        all.extend(jobs("node03", "F", 2));
+        all.extend(jobs("node04", "F", 1));
+        let nodes = node_failures(&all);
+        assert!(find(&nodes, "node03").abnormal);
+        // a single failure isn't enough however clean the rest are
+        assert!(!find(&nodes, "node04").abnormal);
+    }
+
+    #[test]
+    fn node_fail_is_always_flagged() {
+        let mut all = jobs("node[01-02]", "CD", 5);
+        all.extend(jobs("node03", "NF", 1));
+        all.extend(jobs("node03", "CD", 9));
+        let nodes = node_failures(&all);
+        let node03 = find(&nodes, "node03");
+        // one failure is below MIN_FLAGGED_FAILURES, but slurm blamed the node for it
+        assert!(node03.abnormal);
+        assert_eq!(node03.failures(), 1);
+        assert_eq!(nodes[0].node, "node03");
+    }
+
+    #[test]
+    fn exclude_compresses_the_flagged_nodes() {
+        let node = |node: &str, abnormal| NodeFailures {
+            node: node.to_string(),
+            abnormal,
+            ..NodeFailures::default()
+        };
+        let nodes = [
+            node("node012", true),
+            node("node013", true),
+            node("node020", true),
+            node("node014", false),
+        ];
+        assert_eq!(
+            exclude_option(&nodes).as_deref(),
+            Some("--exclude=node[012-013,020]")
+        );
+        assert_eq!(exclude_option(&[]), None);
+    }
+}
@@ -1,20 +1,85 @@
 use crate::{
-    file_watcher::{FileWatcherError, FileWatcherHandle},
-    job_watcher::JobWatcherHandle,
-    slurm::{self, SlurmJob, SlurmJobControlHandle},
+    accounts::{AccountUsage, AccountWatcherHandle},
+    analysis::{node_failures, NodeFailures},
+    cluster::{summarize, ClusterWatcherHandle, NodeInfo, PartitionSummary},
+    columns::{Column, Sort},
+    config::Config,
+    dependency::DependencyGraph,
+    file_watcher::{read_tail, FileWatcherError, FileWatcherHandle},
+    filter::JobFilter,
+    hooks::{HookRun, HookRunnerHandle},
+    job_watcher::{JobListDiff, JobWatcherHandle, TimeWindow},
+    node_watcher::{NodeDetail, NodeWatcherHandle},
+    notification::Notifier,
+    query::JobQuery,
+    search::{search_jobs, SearchHit},
+    slurm::{
+        compress_ranges, expand_hostlist, parse_duration, JobStep, SlurmJob, SlurmJobControlHandle,
+    },
+    stat_watcher::{JobStats, StatWatcherHandle},
     ui::render,
+    watchdog::{log as log_watchdog, WatchdogAction, WatchdogEvent, WatchdogHandle, WatchedJob},
 };
 use crossbeam::{
-    channel::{bounded, unbounded, Receiver, Sender},
+    channel::{unbounded, Receiver, Sender},
     select,
 };
-use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEvent, MouseEventKind};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
 use ratatui::{backend::Backend, widgets::*, Terminal};
-use std::{error, io, path::PathBuf, thread, time::Duration};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    error, io,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+// lines in stderr that are worth surfacing in the job details for a failed job
+const ERROR_PATTERNS: [&str; 12] = [
+    "error",
+    "exception",
+    "traceback",
+    "killed",
+    // not a bare "oom", which is in "room" and "zoom"
+    "oom-kill",
+    "oom_kill",
+    "out of memory",
+    "segmentation fault",
+    "core dumped",
+    "fatal",
+    "abort",
+    "due to time limit",
+];
+
+fn is_error_line(line: &str) -> bool {
+    let line = line.to_lowercase();
+    ERROR_PATTERNS.iter().any(|p| line.contains(p))
+}
+
+// number of sstat samples kept per job for the sparklines
+const STAT_HISTORY: usize = 120;
+
+// how long the row of a job whose state just changed stays highlighted
+const STATE_CHANGE_HIGHLIGHT: Duration = Duration::from_secs(10);
+
+// number of hook runs kept to look back through
+const MAX_HOOK_RUNS: usize = 50;
+
+// number of past filters kept to step back through with the arrow keys
+const MAX_FILTER_HISTORY: usize = 20;
 
 /// Application result type.
 pub type AppResult<T> = std::result::Result<T, Box<dyn error::Error>>;
 
+/// Top level views, switched between with the number keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Jobs,
+    Cluster,
+    // failure rates of the nodes the user's jobs ran on
+    Nodes,
+    Accounts,
+}
+
 #[derive(Debug)]
 pub enum Focus {
     JobList,
@@ -25,23 +90,249 @@ pub enum Focus {
 pub enum RightPanelFocus {
     Output,
     JobScript,
+    Efficiency,
+    Metrics,
+    Dependencies,
+    Nodes,
+    // output of the hooks run on job state changes
+    Hooks,
 }
 
 pub enum AppMessage {
-    // if job list is not empty, return the vec, otherwise None
-    JobList(Option<Vec<SlurmJob>>),
+    // what changed in the job list since the last refresh
+    JobListDiff(JobListDiff),
     // Just return the string, split it later
     OutputFile(Result<String, FileWatcherError>),
     Key(KeyEvent),
     Mouse(MouseEventKind),
     JobCancelled(anyhow::Result<()>),
     JobRequeued(anyhow::Result<()>),
+    JobSignalled(anyhow::Result<()>),
+    TasksResubmitted(anyhow::Result<Resubmission>),
+    // latest sstat sample for each of the polled jobs
+    JobStats(HashMap<String, JobStats>),
+    // every node of every partition from sinfo
+    ClusterInfo(Vec<NodeInfo>),
+    // scontrol details of the nodes of the selected job
+    NodeDetails(Vec<NodeDetail>),
+    // fairshare and this month's usage of the user's accounts
+    AccountUsage(Vec<AccountUsage>),
+    HookFinished(HookRun),
+    WatchdogFired(WatchdogEvent),
+    // when each running job last wrote to its stdout or stderr
+    LastOutput(HashMap<String, SystemTime>),
 }
 
 pub enum JobControlMessage {
-    // pass the jobID to be cancelled
-    CancelJob(String),
-    RequeueJob(SlurmJob),
+    // pass the jobIDs to be cancelled
+    CancelJob(Vec<String>),
+    RequeueJob(Box<SlurmJob>),
+    // resubmit the script of an array job with --array set to the given indices
+    ResubmitTasks(Box<SlurmJob>, String),
+    // signal a job for the watchdog, or cancel it without asking if there is no signal
+    SignalJob(String, Option<String>),
+}
+
+/// Links an array resubmitted with a subset of its tasks back to the original array.
+#[derive(Debug, Clone)]
+pub struct Resubmission {
+    pub original: String,
+    pub new: String,
+    pub indices: String,
+}
+
+/// The tasks of an array job, listed under a single collapsible row in the job table.
+#[derive(Debug, Clone)]
+pub struct ArrayJob {
+    pub array_job_id: String,
+    pub tasks: Vec<SlurmJob>,
+}
+
+impl ArrayJob {
+    /// Number of tasks in each state, e.g. "120 CD, 3 F, 20 R, 57 PD".
+    pub fn state_summary(&self) -> String {
+        let order = ["CD", "F", "TO", "OOM", "NF", "CA", "R", "PD"];
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for task in &self.tasks {
+            match counts.iter_mut().find(|(state, _)| *state == task.state) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((&task.state, 1)),
+            }
+        }
+        counts
+            .sort_by_key(|(state, _)| order.iter().position(|s| s == state).unwrap_or(order.len()));
+        counts
+            .iter()
+            .map(|(state, count)| format!("{} {}", count, state))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    /// A single state to represent the whole array: running or pending while any task is,
+    /// otherwise failed if any task didn't complete and wasn't cancelled, cancelled if any was.
+    pub fn state(&self) -> &str {
+        let any = |state: &str| self.tasks.iter().any(|t| t.state == state);
+        if any("R") {
+            "R"
+        } else if any("PD") {
+            "PD"
+        } else if self
+            .tasks
+            .iter()
+            .any(|t| t.state != "CD" && t.state != "CA")
+        {
+            "F"
+        } else if any("CA") {
+            "CA"
+        } else {
+            "CD"
+        }
+    }
+}
+
+/// Grid of the tasks of an array, one cell per task, shown in place of the job table.
+#[derive(Debug)]
+pub struct ArrayMatrix {
+    pub array_job_id: String,
+    // position of the selected task in the numerically sorted task list
+    pub cursor: usize,
+    // cells per row, set when the grid is rendered so up/down can move a whole row
+    pub columns: usize,
+}
+
+/// A row of the job table: a job, one of the steps of an expanded job or an array of tasks.
+#[derive(Debug, Clone)]
+pub enum JobRow {
+    Job(SlurmJob),
+    Step(SlurmJob, Box<JobStep>),
+    Array(ArrayJob),
+}
+
+impl JobRow {
+    /// The job the row belongs to, for a step this is the parent job.  Array rows stand for
+    /// many jobs so have none.
+    pub fn job(&self) -> Option<&SlurmJob> {
+        match self {
+            JobRow::Job(job) => Some(job),
+            JobRow::Step(job, _) => Some(job),
+            JobRow::Array(_) => None,
+        }
+    }
+
+    /// Slurm id of the row, "<job_id>.<step_id>" for steps so that scancel acts on just the
+    /// step, and the array id for arrays so that it acts on every task.
+    pub fn id(&self) -> String {
+        match self {
+            JobRow::Job(job) => job.job_id.clone(),
+            JobRow::Step(job, step) => format!("{}.{}", job.job_id, step.step_id),
+            JobRow::Array(array) => array.array_job_id.clone(),
+        }
+    }
+
+    /// Key in `App.expanded` that expanding or collapsing this row toggles.
+    pub fn expand_key(&self) -> String {
+        match self {
+            JobRow::Job(job) | JobRow::Step(job, _) => job.job_id.clone(),
+            JobRow::Array(array) => array.array_job_id.clone(),
+        }
+    }
+}
+
+/// What a line typed into the prompt is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptKind {
+    TimeWindow,
+    Query,
+    // a ':' command, e.g. ":filter state:F"
+    Command,
+    // the '/' fuzzy finder
+    Search,
+}
+
+/// A line of text being typed in, shown in a box over the rest of the app.
+#[derive(Debug)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+    // why the last submitted input was rejected
+    pub error: Option<String>,
+    // how far back up the history the input was taken from
+    history_index: Option<usize>,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Self {
+            kind,
+            input: String::new(),
+            error: None,
+            history_index: None,
+        }
+    }
+}
+
+/// Partitions of the cluster overview and the nodes of the selected partition.
+#[derive(Debug, Default)]
+pub struct ClusterView {
+    pub partitions: StatefulTable<PartitionSummary>,
+    pub nodes: StatefulTable<NodeInfo>,
+    // up/down move through the nodes rather than the partitions
+    pub drill_down: bool,
+    all_nodes: Vec<NodeInfo>,
+}
+
+impl ClusterView {
+    pub fn update(&mut self, nodes: Vec<NodeInfo>) {
+        // keep the same partition selected if it is still there
+        let selected = self.partitions.selected().map(|p| p.name.clone());
+        self.partitions.items = summarize(&nodes);
+        let index = selected
+            .and_then(|name| self.partitions.items.iter().position(|p| p.name == name))
+            .unwrap_or(0);
+        self.partitions.state.select(Some(index));
+        self.all_nodes = nodes;
+        self.update_nodes();
+    }
+
+    fn update_nodes(&mut self) {
+        let partition = self.partitions.selected().map(|p| p.name.clone());
+        self.nodes.items = self
+            .all_nodes
+            .iter()
+            .filter(|n| Some(&n.partition) == partition.as_ref())
+            .cloned()
+            .collect();
+        let index = self
+            .nodes
+            .state
+            .selected()
+            .unwrap_or(0)
+            .min(self.nodes.len().saturating_sub(1));
+        self.nodes.state.select(Some(index));
+    }
+
+    pub fn on_up(&mut self) {
+        match self.drill_down {
+            true => self.nodes.previous(),
+            false => {
+                self.partitions.previous();
+                self.nodes.state.select(Some(0));
+                self.update_nodes();
+            }
+        }
+    }
+
+    pub fn on_down(&mut self) {
+        if self.drill_down {
+            if !self.nodes.is_empty() {
+                self.nodes.next();
+            }
+        } else if !self.partitions.is_empty() {
+            self.partitions.next();
+            self.nodes.state.select(Some(0));
+            self.update_nodes();
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -63,13 +354,13 @@ impl<T> StatefulList<T> {
     }
 
     pub fn bottom(&mut self) {
-        self.state.select(Some(self.items.len() - 1));
+        self.state.select(Some(self.items.len().saturating_sub(1)));
     }
 
     pub fn next(&mut self) {
         let i = match self.state.selected() {
             Some(i) => {
-                if i >= self.items.len() - 1 {
+                if i + 1 >= self.items.len() {
                     0
                 } else {
                     i + 1
@@ -84,7 +375,7 @@ impl<T> StatefulList<T> {
         let i = match self.state.selected() {
             Some(i) => {
                 if i == 0 {
-                    self.items.len() - 1
+                    self.items.len().saturating_sub(1)
                 } else {
                     i - 1
                 }
@@ -97,6 +388,10 @@ impl<T> StatefulList<T> {
     pub fn len(&self) -> usize {
         self.items.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
 }
 
 impl IntoIterator for StatefulList<SlurmJob> {
@@ -115,6 +410,10 @@ pub struct StatefulTable<T> {
 }
 
 impl<T> StatefulTable<T> {
+    pub fn selected(&self) -> Option<&T> {
+        self.state.selected().and_then(|i| self.items.get(i))
+    }
+
     pub fn with_items(items: Vec<T>) -> StatefulTable<T> {
         StatefulTable {
             state: TableState::default(),
@@ -124,17 +423,12 @@ impl<T> StatefulTable<T> {
 
     pub fn next(&mut self) {
         let i = match self.state.selected() {
-            Some(i) => {
-                if i >= self.items.len() - 1 {
-                    self.items.len() - 1
-                } else {
-                    i + 1
-                }
-            }
+            Some(i) => i + 1,
             // the only case where it is None is when it is initialised, next should skip to one.
             None => 1,
         };
-        self.state.select(Some(i));
+        self.state
+            .select(Some(i.min(self.items.len().saturating_sub(1))));
     }
 
     pub fn top(&mut self) {
@@ -142,7 +436,7 @@ impl<T> StatefulTable<T> {
     }
 
     pub fn bottom(&mut self) {
-        self.state.select(Some(self.items.len() - 1));
+        self.state.select(Some(self.items.len().saturating_sub(1)));
     }
 
     pub fn previous(&mut self) {
@@ -162,6 +456,10 @@ impl<T> StatefulTable<T> {
     pub fn len(&self) -> usize {
         self.items.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
 }
 
 impl IntoIterator for StatefulTable<SlurmJob> {
@@ -177,68 +475,183 @@ impl IntoIterator for StatefulTable<SlurmJob> {
 #[derive(Debug)]
 pub struct App {
     pub running: bool,
-    pub slurm_jobs: StatefulTable<SlurmJob>,
+    pub slurm_jobs: StatefulTable<JobRow>,
     pub selected_index: usize,
+    // jobs whose steps, or arrays whose tasks, are listed under them in the job table
+    pub expanded: HashSet<String>,
+    // jobs marked to be acted on together instead of the selected row
+    pub marked: HashSet<String>,
+    pub array_matrix: Option<ArrayMatrix>,
     pub job_output: StatefulTable<String>,
     pub job_script: StatefulTable<String>,
+    // last few error looking lines from the stderr of the selected job, if it has finished
+    pub error_lines: Vec<String>,
+    // the job and state the error lines were read for
+    error_lines_job: Option<(String, String)>,
     pub focus: Focus,
     pub right_panel_focus: RightPanelFocus,
     pub cancelling: bool,
+    // what is being cancelled, for the popup
+    pub cancel_targets: Vec<String>,
     pub requeueing: bool,
+    // what is being requeued, for the popup
+    pub requeue_target: String,
+    pub resubmissions: Vec<Resubmission>,
+    // result of the last action, shown in the status bar
+    pub status_message: Option<String>,
     pub output_line_index: usize,
     // special switch for selecting only running jobs
     pub running_only: bool,
+    pub config: Config,
+    pub filter: JobFilter,
+    // filters applied before, most recent last
+    pub filter_history: Vec<String>,
+    // matches of the fuzzy finder, best first, and the one Enter would jump to
+    pub search_hits: Vec<SearchHit>,
+    pub search_selected: usize,
+    // jobs to keep polling live metrics for, regardless of the selection
+    pub pinned: HashSet<String>,
+    // jobs, or whole arrays, whose state changes are notified
+    pub watched: HashSet<String>,
+    // when the jobs whose state changed lately did so, their rows are highlighted for a while
+    pub state_changes: HashMap<String, Instant>,
+    // jobs that finished since a key was last pressed
+    pub finished_unseen: HashSet<String>,
+    // the hooks run lately, most recent last, and them as lines for the hooks panel
+    pub hook_runs: VecDeque<HookRun>,
+    pub hook_log: StatefulTable<String>,
+    pub job_stats: HashMap<String, VecDeque<JobStats>>,
+    pub last_output: HashMap<String, SystemTime>,
+    pub dependency_graph: DependencyGraph,
+    pub view: View,
+    pub prompt: Option<Prompt>,
+    pub cluster: ClusterView,
+    pub node_details: Vec<NodeDetail>,
+    pub node_failures: StatefulTable<NodeFailures>,
+    // every finished job listed since swatch started, whatever the time window and query were at
+    // the time, so the node failure analysis builds up rather than following the job list
+    pub finished_jobs: HashMap<String, SlurmJob>,
+    pub accounts: StatefulTable<AccountUsage>,
     raw_slurm_output: Vec<SlurmJob>,
     receiver: Receiver<AppMessage>,
     input_receiver: Receiver<io::Result<Event>>,
     job_ctrl_receiver: Receiver<AppMessage>,
     job_ctrl_sender: Sender<JobControlMessage>,
+    job_watcher_handle: JobWatcherHandle,
     file_watcher_handle: FileWatcherHandle,
+    stat_watcher_handle: StatWatcherHandle,
+    cluster_watcher_handle: ClusterWatcherHandle,
+    node_watcher_handle: NodeWatcherHandle,
+    account_watcher_handle: AccountWatcherHandle,
+    notifier: Notifier,
+    hook_runner_handle: HookRunnerHandle,
+    watchdog_handle: WatchdogHandle,
 }
 
 impl App {
     /// Constructs a new instance of [`App`].
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         input_rx: Receiver<io::Result<Event>>,
         user: String,
-        time_period: usize,
+        time_window: TimeWindow,
+        query: JobQuery,
         slurm_refresh: u64,
         file_refresh_rate: u64,
+        stat_refresh_rate: u64,
         running_only: bool,
+        config: Config,
     ) -> Self {
         let (sender, receiver) = unbounded();
 
         // sender gets used for the job watcher and slurm watcher threads.
-        let _ = JobWatcherHandle::new(
+        let job_watcher_handle = JobWatcherHandle::new(
             sender.clone(),
             Duration::from_secs(slurm_refresh),
             user.clone(),
-            time_period,
+            time_window,
+            query,
         );
         let file_watcher_handle =
             FileWatcherHandle::new(sender.clone(), Duration::from_secs(file_refresh_rate));
+        let stat_watcher_handle =
+            StatWatcherHandle::new(sender.clone(), Duration::from_secs(stat_refresh_rate));
+        let cluster_watcher_handle =
+            ClusterWatcherHandle::new(sender.clone(), Duration::from_secs(slurm_refresh));
+        let node_watcher_handle =
+            NodeWatcherHandle::new(sender.clone(), Duration::from_secs(stat_refresh_rate));
+        // month totals change slowly and sreport is expensive, so poll much less often
+        let account_watcher_handle = AccountWatcherHandle::new(
+            sender.clone(),
+            Duration::from_secs(slurm_refresh * 10),
+            user.clone(),
+        );
+        let hook_runner_handle = HookRunnerHandle::new(sender.clone());
+        let watchdog_handle = WatchdogHandle::new(
+            sender.clone(),
+            Duration::from_secs(file_refresh_rate),
+            config.watchdog.rules.clone(),
+        );
         let (job_ctrl_send, job_ctrl_recv) = unbounded();
         let (job_ctrl_instr_send, job_ctrl_reply_recv) = unbounded();
         let _ = SlurmJobControlHandle::new(job_ctrl_send.clone(), job_ctrl_reply_recv.clone());
 
         Self {
             running: true,
-            slurm_jobs: StatefulTable::<SlurmJob>::default(),
+            slurm_jobs: StatefulTable::with_items(Vec::new()),
             selected_index: 0,
+            expanded: HashSet::new(),
+            marked: HashSet::new(),
+            array_matrix: None,
             focus: Focus::JobList,
             right_panel_focus: RightPanelFocus::Output,
             cancelling: false,
+            cancel_targets: Vec::new(),
             requeueing: false,
+            requeue_target: String::new(),
+            resubmissions: Vec::new(),
+            status_message: None,
             output_line_index: 0,
             running_only,
+            config,
+            filter: JobFilter::default(),
+            filter_history: Vec::new(),
+            search_hits: Vec::new(),
+            search_selected: 0,
+            pinned: HashSet::new(),
+            watched: HashSet::new(),
+            state_changes: HashMap::new(),
+            finished_unseen: HashSet::new(),
+            hook_runs: VecDeque::new(),
+            hook_log: StatefulTable::with_items(Vec::new()),
+            job_stats: HashMap::new(),
+            last_output: HashMap::new(),
+            dependency_graph: DependencyGraph::default(),
+            view: View::Jobs,
+            prompt: None,
+            cluster: ClusterView::default(),
+            node_details: Vec::new(),
+            node_failures: StatefulTable::default(),
+            finished_jobs: HashMap::new(),
+            accounts: StatefulTable::default(),
             job_output: StatefulTable::<String>::default(),
             job_script: StatefulTable::<String>::default(),
+            error_lines: Vec::new(),
+            error_lines_job: None,
             raw_slurm_output: Vec::new(),
             receiver,
             input_receiver: input_rx,
             job_ctrl_receiver: job_ctrl_recv,
             job_ctrl_sender: job_ctrl_instr_send,
+            job_watcher_handle,
             file_watcher_handle,
+            stat_watcher_handle,
+            cluster_watcher_handle,
+            node_watcher_handle,
+            account_watcher_handle,
+            notifier: Notifier::new(),
+            hook_runner_handle,
+            watchdog_handle,
         }
     }
 
@@ -253,7 +666,7 @@ impl App {
                 recv(self.input_receiver) -> input_res => {
                     match input_res.unwrap().unwrap() {
                         Event::Key(key_event) => {
-                            if (key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL) || key_event.code == KeyCode::Char('q') {
+                            if (key_event.code == KeyCode::Char('c') && key_event.modifiers == KeyModifiers::CONTROL) || (key_event.code == KeyCode::Char('q') && self.prompt.is_none()) {
                                 return Ok(());
                             } else  {
                                  self.handle(AppMessage::Key(key_event));
@@ -268,16 +681,33 @@ impl App {
                 // handle from the job control receiver thread
                 recv(self.job_ctrl_receiver) -> job_ctrl_msg => {
                     match job_ctrl_msg.unwrap() {
-                        AppMessage::JobCancelled(result) => {
+                        AppMessage::JobCancelled(_) => {
+                            // marks are used up by the action they were made for, whatever the
+                            // outcome, so that a later 'c' can't act on them unexpectedly
+                            self.cancelling = false;
+                            self.marked.clear();
+                        }
+                        AppMessage::TasksResubmitted(result) => {
+                            self.requeueing = false;
+                            self.marked.clear();
                             match result {
-                                Ok(_) => {
-                                    self.cancelling = false;
+                                Ok(resubmission) => {
+                                    self.status_message = Some(format!(
+                                        "Resubmitted tasks {} of {} as {}",
+                                        resubmission.indices,
+                                        resubmission.original,
+                                        resubmission.new
+                                    ));
+                                    self.resubmissions.push(resubmission);
                                 }
-                                Err(_) => {
-                                    self.cancelling = false;
+                                Err(e) => {
+                                    self.status_message = Some(e.to_string());
                                 }
                             }
                         }
+                        AppMessage::JobSignalled(Err(e)) => {
+                            self.status_message = Some(e.to_string());
+                        }
                         AppMessage::JobRequeued(result) => {
                             match result {
                                 Ok(_) => {
@@ -298,27 +728,285 @@ impl App {
     fn build_job_table(&mut self) {
         // convert the vec of slurm jobs into a stateful table, keeping track of the pointer.  This
         // either happens when building a new table or converting between filtering methods
-        if self.running_only {
-            self.slurm_jobs.items = self
-                .raw_slurm_output
-                .iter()
-                .filter(|j| ["R", "PD"].contains(&j.state.as_str()))
-                .cloned()
-                .collect();
-        } else {
-            self.slurm_jobs.items = self.raw_slurm_output.clone();
+        let mut jobs = self
+            .raw_slurm_output
+            .iter()
+            .filter(|j| !self.running_only || ["R", "PD"].contains(&j.state.as_str()))
+            .filter(|j| self.filter.matches(j))
+            .collect::<Vec<&SlurmJob>>();
+        // the job watcher lists jobs by id, an array goes where its first task sorts to
+        if let Some(sort) = self.config.sort {
+            jobs.sort_by(|a, b| sort.compare(a, b, &self.last_output));
+        }
+        // a mark on a job that has left the list, or been filtered out of it, would be acted on
+        // without being seen
+        let listed: HashSet<&str> = jobs.iter().map(|j| j.job_id.as_str()).collect();
+        self.marked.retain(|id| listed.contains(id.as_str()));
+        // group the tasks of each array together, keeping the order the arrays first appear in
+        let mut groups: Vec<(Option<&str>, Vec<&SlurmJob>)> = Vec::new();
+        for job in jobs {
+            let array_job_id = job.array_job_id();
+            match groups
+                .iter_mut()
+                .find(|(id, _)| array_job_id.is_some() && *id == array_job_id)
+            {
+                Some((_, tasks)) => tasks.push(job),
+                None => groups.push((array_job_id, vec![job])),
+            }
         }
-        if self.selected_index > self.slurm_jobs.len() - 1 {
-            self.selected_index = self.slurm_jobs.len() - 1;
-            self.slurm_jobs.state.select(Some(self.selected_index))
+        let mut rows = Vec::new();
+        for (array_job_id, mut jobs) in groups {
+            if let Some(array_job_id) = array_job_id {
+                jobs.sort_by_key(|j| j.sort_key());
+                rows.push(JobRow::Array(ArrayJob {
+                    array_job_id: array_job_id.to_string(),
+                    tasks: jobs.iter().map(|&j| j.clone()).collect(),
+                }));
+                if !self.expanded.contains(array_job_id) {
+                    continue;
+                }
+            }
+            for job in jobs {
+                rows.push(JobRow::Job(job.clone()));
+                if self.expanded.contains(&job.job_id) {
+                    rows.extend(
+                        job.steps
+                            .iter()
+                            .map(|step| JobRow::Step(job.clone(), Box::new(step.clone()))),
+                    );
+                }
+            }
+        }
+        let previous = std::mem::replace(&mut self.slurm_jobs.items, rows);
+        self.reselect(&previous);
+    }
+
+    fn on_job_list_diff(&mut self, diff: JobListDiff) {
+        let now = Instant::now();
+        self.state_changes
+            .retain(|_, at| now.duration_since(*at) < STATE_CHANGE_HIGHLIGHT);
+        for change in diff.changed.iter().filter(|c| c.state_changed()) {
+            self.state_changes.insert(change.job.job_id.clone(), now);
+            if change.finished() {
+                self.finished_unseen.insert(change.job.job_id.clone());
+            }
+        }
+        self.notify(&diff);
+        self.run_hooks(&diff);
+        diff.apply(&mut self.raw_slurm_output);
+        self.dependency_graph = DependencyGraph::new(&self.raw_slurm_output);
+        for job in self.raw_slurm_output.iter().filter(|j| j.is_finished()) {
+            self.finished_jobs.insert(job.job_id.clone(), job.clone());
+        }
+        self.update_node_failures();
+        self.build_job_table();
+    }
+
+    fn notify(&self, diff: &JobListDiff) {
+        let notify = &self.config.notify;
+        let changes = diff
+            .changed
+            .iter()
+            .filter(|c| c.state_changed() && notify.wants(&c.old_state, &c.job.state))
+            .filter(|c| !notify.watched_only || self.is_watched(&c.job))
+            .map(|c| {
+                format!(
+                    "{} ({}) {} → {}",
+                    c.job.job_id, c.job.job_name, c.old_state, c.job.state
+                )
+            })
+            .collect::<Vec<String>>();
+        // a whole array finishing at once is one notification, not hundreds
+        let body = match changes.len() {
+            0 => return,
+            1..=3 => changes.join(", "),
+            n => format!("{}, and {} more", changes[..3].join(", "), n - 3),
+        };
+        self.notifier.send(&notify.methods, "swatch", &body);
+    }
+
+    fn run_hooks(&self, diff: &JobListDiff) {
+        for change in diff.changed.iter().filter(|c| c.state_changed()) {
+            for hook in &self.config.hooks {
+                if hook.applies(&change.job, &change.old_state) {
+                    self.hook_runner_handle.run(
+                        hook.clone(),
+                        change.job.clone(),
+                        change.old_state.clone(),
+                    );
+                }
+            }
+        }
+    }
+
+    fn on_hook_finished(&mut self, run: HookRun) {
+        if !run.succeeded() {
+            self.status_message = Some(format!(
+                "Hook for {} {}: {}",
+                run.job_id, run.outcome, run.command
+            ));
+        }
+        self.hook_runs.push_back(run);
+        if self.hook_runs.len() > MAX_HOOK_RUNS {
+            self.hook_runs.pop_front();
+        }
+        // newest first, each run's output under a line saying what ran
+        self.hook_log.items = self
+            .hook_runs
+            .iter()
+            .rev()
+            .flat_map(|run| {
+                let heading = format!(
+                    "{} {} {} → {} {} ({:.1}s): {}",
+                    run.started.format("%H:%M:%S"),
+                    run.job_id,
+                    run.old_state,
+                    run.new_state,
+                    run.outcome,
+                    run.duration.as_secs_f64(),
+                    run.command
+                );
+                std::iter::once(heading).chain(run.output.iter().map(|l| format!("  {}", l)))
+            })
+            .collect();
+    }
+
+    fn get_watchdog_jobs(&self) -> Vec<WatchedJob> {
+        self.raw_slurm_output
+            .iter()
+            .filter(|j| j.state == "R")
+            .map(WatchedJob::new)
+            .collect()
+    }
+
+    /// Log what a watchdog rule did and do it, unless this is a dry run.
+    fn on_watchdog_fired(&mut self, event: WatchdogEvent) {
+        let dry_run = self.config.watchdog.dry_run;
+        self.status_message = Some(match log_watchdog(&event, dry_run) {
+            Ok(_) if dry_run => format!("Watchdog (dry run): {}", event),
+            Ok(_) => format!("Watchdog: {}", event),
+            Err(e) => format!("Watchdog: {} (not logged: {})", event, e),
+        });
+        if dry_run {
+            return;
+        }
+        match event.action {
+            WatchdogAction::Cancel => self
+                .job_ctrl_sender
+                .send(JobControlMessage::SignalJob(event.job_id, None))
+                .unwrap(),
+            WatchdogAction::Signal => self
+                .job_ctrl_sender
+                .send(JobControlMessage::SignalJob(
+                    event.job_id,
+                    Some(event.signal),
+                ))
+                .unwrap(),
+            WatchdogAction::Alert => {
+                let body = format!("{} ({}): {}", event.job_id, event.job_name, event.reason);
+                self.notifier
+                    .send(&self.config.notify.methods, "swatch watchdog", &body);
+            }
         }
     }
 
+    /// How long ago a running job last wrote to its stdout or stderr.
+    pub fn output_age(&self, job: &SlurmJob) -> Option<Duration> {
+        if job.state != "R" {
+            return None;
+        }
+        let modified = self.last_output.get(&job.job_id)?;
+        Some(
+            SystemTime::now()
+                .duration_since(*modified)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Whether a running job's output has been quiet for long enough to look hung.
+    pub fn is_stalled(&self, job: &SlurmJob) -> bool {
+        self.output_age(job).is_some_and(|age| {
+            self.config
+                .stall
+                .is_stalled(age, parse_duration(&job.elapsed_time))
+        })
+    }
+
+    /// Whether the job's state changed recently enough for its row to still be highlighted.
+    pub fn state_just_changed(&self, job_id: &str) -> bool {
+        self.state_changes
+            .get(job_id)
+            .is_some_and(|at| at.elapsed() < STATE_CHANGE_HIGHLIGHT)
+    }
+
+    /// Put the selection back on the row it was on before the table was rebuilt, wherever that
+    /// row has moved to, so a new job or a changed filter can't slide another job under the
+    /// cursor.  If the row has gone, a step falls back to its job and a task to its array,
+    /// otherwise the nearest row below it that is still there, or above it, is selected.
+    fn reselect(&mut self, previous: &[JobRow]) {
+        let Some(selected) = previous.get(self.selected_index) else {
+            self.selected_index = self
+                .selected_index
+                .min(self.slurm_jobs.len().saturating_sub(1));
+            self.slurm_jobs.state.select(Some(self.selected_index));
+            return;
+        };
+        let mut candidates = vec![selected.id()];
+        if let Some(job) = selected.job() {
+            candidates.push(job.job_id.clone());
+            candidates.extend(job.array_job_id().map(|id| id.to_string()));
+        }
+        candidates.extend(previous[self.selected_index + 1..].iter().map(|r| r.id()));
+        candidates.extend(previous[..self.selected_index].iter().rev().map(|r| r.id()));
+        let positions = self
+            .slurm_jobs
+            .items
+            .iter()
+            .enumerate()
+            .map(|(index, row)| (row.id(), index))
+            .collect::<HashMap<String, usize>>();
+        self.selected_index = candidates
+            .iter()
+            .find_map(|id| positions.get(id).copied())
+            .unwrap_or(0);
+        self.slurm_jobs.state.select(Some(self.selected_index));
+    }
+
     pub fn handle(&mut self, msg: AppMessage) {
+        // using the app counts as having looked at the jobs that finished, the mouse just
+        // passing over the terminal doesn't
+        if matches!(
+            msg,
+            AppMessage::Key(_)
+                | AppMessage::Mouse(
+                    MouseEventKind::Down(_)
+                        | MouseEventKind::Up(_)
+                        | MouseEventKind::ScrollUp
+                        | MouseEventKind::ScrollDown
+                        | MouseEventKind::ScrollLeft
+                        | MouseEventKind::ScrollRight
+                )
+        ) {
+            self.finished_unseen.clear();
+        }
         match msg {
-            AppMessage::JobList(job_list) => {
-                if let Some(job_list) = job_list {
-                    self.raw_slurm_output = job_list;
+            AppMessage::JobListDiff(diff) => {
+                self.on_job_list_diff(diff);
+            }
+            AppMessage::HookFinished(run) => {
+                self.on_hook_finished(run);
+            }
+            AppMessage::WatchdogFired(event) => {
+                self.on_watchdog_fired(event);
+            }
+            AppMessage::LastOutput(last_output) => {
+                self.last_output = last_output;
+                // the order changes with the times when the list is sorted by them
+                if self
+                    .config
+                    .sort
+                    .is_some_and(|s| s.column == Column::LastOutput)
+                {
                     self.build_job_table();
                 }
             }
@@ -328,6 +1016,89 @@ impl App {
                     Err(e) => vec![e.to_string()],
                 };
             }
+            AppMessage::JobStats(stats) => {
+                let tracked = self.get_stat_jobs();
+                self.job_stats.retain(|job_id, _| tracked.contains(job_id));
+                for (job_id, sample) in stats {
+                    let history = self.job_stats.entry(job_id).or_default();
+                    history.push_back(sample);
+                    if history.len() > STAT_HISTORY {
+                        history.pop_front();
+                    }
+                }
+            }
+            AppMessage::ClusterInfo(nodes) => {
+                self.cluster.update(nodes);
+            }
+            AppMessage::NodeDetails(nodes) => {
+                self.node_details = nodes;
+            }
+            AppMessage::AccountUsage(accounts) => {
+                let index = self
+                    .accounts
+                    .state
+                    .selected()
+                    .unwrap_or(0)
+                    .min(accounts.len().saturating_sub(1));
+                self.accounts.items = accounts;
+                self.accounts.state.select(Some(index));
+            }
+            AppMessage::Key(key_event) if self.prompt.is_some() => {
+                self.on_prompt_key(key_event);
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char('T') => {
+                self.prompt = Some(Prompt::new(PromptKind::TimeWindow));
+            }
+            AppMessage::Key(key_event)
+                if key_event.code == KeyCode::Char('/') && self.view == View::Jobs =>
+            {
+                self.search_hits.clear();
+                self.prompt = Some(Prompt::new(PromptKind::Search));
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char(':') => {
+                self.prompt = Some(Prompt::new(PromptKind::Command));
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char('F') => {
+                let mut prompt = Prompt::new(PromptKind::Query);
+                // start from the current query so it can be tweaked rather than retyped
+                prompt.input = self.query().to_string();
+                self.prompt = Some(prompt);
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char('[') => {
+                self.step_time_window(-1);
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char(']') => {
+                self.step_time_window(1);
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char('1') => {
+                self.view = View::Jobs;
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char('2') => {
+                self.view = View::Cluster;
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char('3') => {
+                self.view = View::Nodes;
+            }
+            AppMessage::Key(key_event) if key_event.code == KeyCode::Char('4') => {
+                self.view = View::Accounts;
+            }
+            AppMessage::Key(key_event) if self.view == View::Accounts => match key_event.code {
+                KeyCode::Up => self.accounts.previous(),
+                KeyCode::Down if !self.accounts.is_empty() => self.accounts.next(),
+                _ => {}
+            },
+            AppMessage::Key(key_event) if self.view == View::Nodes => match key_event.code {
+                KeyCode::Up if !self.node_failures.is_empty() => self.node_failures.previous(),
+                KeyCode::Down if !self.node_failures.is_empty() => self.node_failures.next(),
+                _ => {}
+            },
+            AppMessage::Key(key_event) if self.view == View::Cluster => match key_event.code {
+                KeyCode::Up => self.cluster.on_up(),
+                KeyCode::Down => self.cluster.on_down(),
+                KeyCode::Enter => self.cluster.drill_down = !self.cluster.nodes.is_empty(),
+                KeyCode::Esc => self.cluster.drill_down = false,
+                _ => {}
+            },
             AppMessage::Mouse(mouse_event) => match mouse_event {
                 MouseEventKind::ScrollUp => {
                     self.on_up();
@@ -337,91 +1108,527 @@ impl App {
                 }
                 _ => {}
             },
-            AppMessage::Key(key_event) => {
-                if !self.cancelling && !self.requeueing {
-                    match key_event.code {
-                        KeyCode::Char('c') | KeyCode::Char('C') => {
-                            self.cancelling = true;
-                            self.job_ctrl_sender
-                                .send(JobControlMessage::CancelJob(
-                                    self.slurm_jobs.items[self.selected_index].job_id.clone(),
-                                ))
-                                .unwrap();
-                        }
-                        KeyCode::Char('j') => {
-                            self.on_j();
-                        }
-                        KeyCode::Down => {
-                            if key_event.modifiers == KeyModifiers::SHIFT {
-                                self.on_shift_down();
-                            } else {
-                                self.on_down();
-                            }
-                        }
-                        KeyCode::Char('t') => {
-                            self.on_t();
-                        }
-                        KeyCode::Char('f') => {
-                            self.on_f();
-                        }
-                        KeyCode::Char('b') => {
-                            self.on_b();
+            AppMessage::Key(key_event) if !self.cancelling && !self.requeueing => {
+                match key_event.code {
+                    KeyCode::Char('c') | KeyCode::Char('C') => {
+                        self.cancel_jobs(self.get_action_ids());
+                    }
+                    KeyCode::Char('X') => {
+                        self.on_cancel_with_dependents();
+                    }
+                    KeyCode::Char('s') => {
+                        self.on_s();
+                    }
+                    KeyCode::Char('S') => {
+                        self.on_shift_s();
+                    }
+                    KeyCode::Char('j') => {
+                        self.on_j();
+                    }
+                    KeyCode::Down => {
+                        if key_event.modifiers == KeyModifiers::SHIFT {
+                            self.on_shift_down();
+                        } else {
+                            self.on_down();
                         }
-                        KeyCode::Char('r') => {
+                    }
+                    KeyCode::Char('t') => {
+                        self.on_t();
+                    }
+                    KeyCode::Char('f') => {
+                        self.on_f();
+                    }
+                    KeyCode::Char('b') => {
+                        self.on_b();
+                    }
+                    KeyCode::Char('r') if self.marks_are_array_tasks() => {
+                        self.on_resubmit_marked();
+                    }
+                    KeyCode::Char('r') => {
+                        // resubmitting any task of an array resubmits the whole array script
+                        let job = match self.slurm_jobs.items.get(self.selected_index) {
+                            Some(JobRow::Array(array)) => array.tasks.first(),
+                            Some(row) => row.job(),
+                            None => None,
+                        };
+                        if let Some(job) = job {
                             self.requeueing = true;
+                            self.requeue_target = job.job_id.clone();
                             self.job_ctrl_sender
-                                .send(JobControlMessage::RequeueJob(
-                                    self.slurm_jobs.items[self.selected_index].clone(),
-                                ))
+                                .send(JobControlMessage::RequeueJob(Box::new(job.clone())))
                                 .unwrap();
                         }
-                        KeyCode::Up => {
-                            if key_event.modifiers == KeyModifiers::SHIFT {
-                                self.on_shift_up();
-                            } else {
-                                self.on_up();
-                            }
-                        }
-                        KeyCode::Tab => {
-                            self.toggle_focus();
+                    }
+                    KeyCode::Char('R') => {
+                        self.on_resubmit_failed();
+                    }
+                    KeyCode::Up => {
+                        if key_event.modifiers == KeyModifiers::SHIFT {
+                            self.on_shift_up();
+                        } else {
+                            self.on_up();
                         }
-                        _ => {}
                     }
+                    KeyCode::Tab => {
+                        self.toggle_focus();
+                    }
+                    KeyCode::Enter => {
+                        self.on_enter();
+                    }
+                    KeyCode::Char(' ') => {
+                        self.on_space();
+                    }
+                    KeyCode::Char('p') => {
+                        self.on_p();
+                    }
+                    KeyCode::Char('w') => {
+                        self.on_w();
+                    }
+                    KeyCode::Char('m') => {
+                        self.on_m();
+                    }
+                    KeyCode::Left if self.array_matrix.is_some() => {
+                        self.move_matrix_cursor(-1);
+                    }
+                    KeyCode::Right if self.array_matrix.is_some() => {
+                        self.move_matrix_cursor(1);
+                    }
+                    _ => {}
                 }
             }
             _ => {}
         }
+        self.cluster_watcher_handle
+            .set_active(self.view == View::Cluster);
+        self.account_watcher_handle
+            .set_active(self.view == View::Accounts);
+        // only look the nodes up while they are shown
+        let nodes = match self.right_panel_focus {
+            RightPanelFocus::Nodes => self.get_selected_nodes(),
+            _ => Vec::new(),
+        };
+        self.node_watcher_handle.set_nodes(nodes);
+        // the watchdog checks when every running job last wrote anything, for the stall indicator,
+        // and reads the output of the ones its rules cover
+        let watched = self.get_watchdog_jobs();
+        self.watchdog_handle.set_jobs(watched);
         // update the job watcher
         let curr_output_file = self.get_output_file_path();
         self.file_watcher_handle.set_file_path(curr_output_file);
+        // poll sstat for the selected and pinned running jobs
+        let stat_jobs = self.get_stat_jobs();
+        self.stat_watcher_handle.set_jobs(stat_jobs);
         // update the job script table to point at the currently selected job
         self.get_job_script();
+        self.get_error_lines();
+    }
+
+    /// The job of the selected row, for a step this is its parent job.  While the array matrix
+    /// is open this is the task under its cursor.
+    pub fn selected_job(&self) -> Option<&SlurmJob> {
+        if let Some(matrix) = &self.array_matrix {
+            return self
+                .get_matrix_tasks(&matrix.array_job_id)
+                .get(matrix.cursor)
+                .copied();
+        }
+        self.slurm_jobs
+            .items
+            .get(self.selected_index)
+            .and_then(|row| row.job())
+    }
+
+    /// Every task of an array, sorted by task id.
+    pub fn get_matrix_tasks(&self, array_job_id: &str) -> Vec<&SlurmJob> {
+        let mut tasks: Vec<&SlurmJob> = self
+            .raw_slurm_output
+            .iter()
+            .filter(|j| j.array_job_id() == Some(array_job_id))
+            .collect();
+        tasks.sort_by_key(|j| j.sort_key());
+        tasks
+    }
+
+    /// Ids that cancel and other actions apply to: the marked jobs if there are any, otherwise
+    /// the selected row (which for an array row means the whole array) or matrix task.  Marks
+    /// are only ever on listed jobs, and are cleared once an action has used them.
+    pub fn get_action_ids(&self) -> Vec<String> {
+        if !self.marked.is_empty() {
+            let mut ids: Vec<String> = self.marked.iter().cloned().collect();
+            ids.sort();
+            return ids;
+        }
+        if self.array_matrix.is_some() {
+            return self
+                .selected_job()
+                .map(|j| vec![j.job_id.clone()])
+                .unwrap_or_default();
+        }
+        self.slurm_jobs
+            .items
+            .get(self.selected_index)
+            .map(|row| vec![row.id()])
+            .unwrap_or_default()
+    }
+
+    pub fn time_window(&self) -> &TimeWindow {
+        self.job_watcher_handle.time_window()
+    }
+
+    pub fn query(&self) -> &JobQuery {
+        self.job_watcher_handle.query()
+    }
+
+    pub fn on_prompt_key(&mut self, key_event: KeyEvent) {
+        let Some(prompt) = self.prompt.as_mut() else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => {
+                self.prompt = None;
+                self.search_hits.clear();
+            }
+            KeyCode::Enter => {
+                let input = prompt.input.clone();
+                match prompt.kind {
+                    PromptKind::Search => match self.search_hits.get(self.search_selected) {
+                        Some(hit) => {
+                            let id = hit.id.clone();
+                            self.select_job(&id);
+                            self.prompt = None;
+                            self.search_hits.clear();
+                        }
+                        None => prompt.error = Some("no job matches".to_string()),
+                    },
+                    PromptKind::TimeWindow => match input.parse::<TimeWindow>() {
+                        Ok(time_window) => {
+                            self.set_time_window(time_window);
+                            self.prompt = None;
+                        }
+                        Err(e) => prompt.error = Some(e),
+                    },
+                    PromptKind::Query => match input.parse::<JobQuery>() {
+                        Ok(query) => {
+                            self.status_message = Some(match query.is_empty() {
+                                true => "Query cleared".to_string(),
+                                false => format!("Query: {}", query),
+                            });
+                            self.job_watcher_handle.set_query(query);
+                            self.prompt = None;
+                        }
+                        Err(e) => prompt.error = Some(e),
+                    },
+                    PromptKind::Command => match self.run_command(&input) {
+                        Ok(()) => self.prompt = None,
+                        Err(e) => {
+                            if let Some(prompt) = self.prompt.as_mut() {
+                                prompt.error = Some(e);
+                            }
+                        }
+                    },
+                }
+            }
+            KeyCode::Up if prompt.kind == PromptKind::Search => {
+                self.search_selected = self.search_selected.saturating_sub(1);
+            }
+            KeyCode::Down
+                if prompt.kind == PromptKind::Search
+                    && self.search_selected + 1 < self.search_hits.len() =>
+            {
+                self.search_selected += 1;
+            }
+            KeyCode::Up | KeyCode::Down if prompt.kind == PromptKind::Command => {
+                let history = &self.filter_history;
+                if history.is_empty() {
+                    return;
+                }
+                let index = match (key_event.code, prompt.history_index) {
+                    (KeyCode::Up, None) => Some(history.len() - 1),
+                    (KeyCode::Up, Some(i)) => Some(i.saturating_sub(1)),
+                    (_, Some(i)) if i + 1 < history.len() => Some(i + 1),
+                    _ => None,
+                };
+                prompt.history_index = index;
+                prompt.input = match index {
+                    Some(i) => format!("filter {}", history[i]),
+                    None => String::new(),
+                };
+            }
+            KeyCode::Backspace => {
+                prompt.input.pop();
+            }
+            KeyCode::Char(c) => prompt.input.push(c),
+            _ => {}
+        }
+        // the finder re-ranks on every key press
+        if let Some(prompt) = self
+            .prompt
+            .as_ref()
+            .filter(|p| p.kind == PromptKind::Search)
+        {
+            if matches!(key_event.code, KeyCode::Char(_) | KeyCode::Backspace) {
+                self.search_hits = search_jobs(&self.slurm_jobs.items, &prompt.input);
+                self.search_selected = 0;
+            }
+        }
+    }
+
+    /// Move the selection to a job, expanding its array if the task is hidden in it.
+    pub fn select_job(&mut self, job_id: &str) {
+        let find = |app: &App| {
+            app.slurm_jobs
+                .items
+                .iter()
+                .position(|row| !matches!(row, JobRow::Step(..)) && row.id() == job_id)
+        };
+        let mut index = find(self);
+        if index.is_none() {
+            if let Some((array_job_id, _)) = job_id.split_once('_') {
+                self.expanded.insert(array_job_id.to_string());
+                self.build_job_table();
+                index = find(self);
+            }
+        }
+        if let Some(index) = index {
+            self.focus = Focus::JobList;
+            self.selected_index = index;
+            self.slurm_jobs.state.select(Some(index));
+        }
+    }
+
+    /// Sort by the next of the shown columns, back to the job watcher's order after the last.
+    pub fn on_s(&mut self) {
+        let columns = &self.config.columns;
+        let next = match self.config.sort {
+            None => columns.first(),
+            Some(sort) => columns.iter().skip_while(|c| **c != sort.column).nth(1),
+        };
+        self.config.sort = next.map(|column| Sort {
+            column: *column,
+            descending: self.config.sort.is_some_and(|s| s.descending),
+        });
+        self.save_config();
+        self.build_job_table();
+    }
+
+    /// Flip the sort between ascending and descending.
+    pub fn on_shift_s(&mut self) {
+        let sort = self.config.sort.unwrap_or(Sort {
+            column: Column::Id,
+            descending: false,
+        });
+        self.config.sort = Some(Sort {
+            descending: !sort.descending,
+            ..sort
+        });
+        self.save_config();
+        self.build_job_table();
+    }
+
+    fn save_config(&mut self) {
+        if let Err(e) = self.config.save() {
+            self.status_message = Some(format!("Couldn't save the config: {}", e));
+        }
+    }
+
+    /// Run a command typed after ':', `filter [terms]`, `columns <names>` or
+    /// `sort [column] [asc|desc]`.
+    fn run_command(&mut self, input: &str) -> Result<(), String> {
+        let (command, args) = input
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((input.trim(), ""));
+        match command {
+            "filter" | "f" => {
+                self.filter = args.parse::<JobFilter>()?;
+                if !self.filter.is_empty() {
+                    let filter = self.filter.to_string();
+                    self.filter_history.retain(|f| *f != filter);
+                    self.filter_history.push(filter);
+                    if self.filter_history.len() > MAX_FILTER_HISTORY {
+                        self.filter_history.remove(0);
+                    }
+                }
+                self.build_job_table();
+                Ok(())
+            }
+            "columns" if args.trim().is_empty() => Err(format!(
+                "columns: expected some of {}",
+                Column::ALL.map(|c| c.name()).join(", ")
+            )),
+            "columns" => {
+                let columns = args
+                    .split([',', ' '])
+                    .filter(|c| !c.is_empty())
+                    .map(|c| c.parse::<Column>())
+                    .collect::<Result<Vec<Column>, String>>()?;
+                // the id column carries the tree of arrays and steps
+                if !columns.contains(&Column::Id) {
+                    return Err("the columns have to include id".to_string());
+                }
+                self.config.columns = columns;
+                self.save_config();
+                Ok(())
+            }
+            "sort" => {
+                let mut words = args.split_whitespace();
+                self.config.sort = match words.next() {
+                    None => None,
+                    Some(column) => Some(Sort {
+                        column: column.parse()?,
+                        descending: match words.next() {
+                            None | Some("asc") => false,
+                            Some("desc") => true,
+                            Some(order) => return Err(format!("{}: expected asc or desc", order)),
+                        },
+                    }),
+                };
+                self.save_config();
+                self.build_job_table();
+                Ok(())
+            }
+            "" => Ok(()),
+            command => Err(format!(
+                "{}: unknown command, expected filter, columns or sort",
+                command
+            )),
+        }
+    }
+
+    fn set_time_window(&mut self, time_window: TimeWindow) {
+        self.status_message = Some(format!("Time window: {}", time_window));
+        self.job_watcher_handle.set_time_window(time_window);
+    }
+
+    /// Widen or narrow the time window to the next of a few common durations.
+    pub fn step_time_window(&mut self, step: isize) {
+        let hours = [1, 6, 12, 24, 72, 168, 336, 720];
+        let presets = hours.map(|h| Duration::from_secs(h * 3600));
+        // a date range steps from the default rather than its own length
+        let current = match self.time_window() {
+            TimeWindow::Last(duration) => *duration,
+            TimeWindow::Range { .. } => presets[3],
+        };
+        let index = match step > 0 {
+            true => presets.iter().position(|p| *p > current),
+            false => presets.iter().rposition(|p| *p < current),
+        };
+        if let Some(index) = index {
+            self.set_time_window(TimeWindow::Last(presets[index]));
+        }
+    }
+
+    fn update_node_failures(&mut self) {
+        // stay on the same node as the ranking changes
+        let selected = self.node_failures.selected().map(|n| n.node.clone());
+        self.node_failures.items = node_failures(self.finished_jobs.values());
+        let index = selected
+            .and_then(|node| self.node_failures.items.iter().position(|n| n.node == node))
+            .unwrap_or(0);
+        self.node_failures
+            .state
+            .select((!self.node_failures.is_empty()).then_some(index));
+    }
+
+    /// Finished jobs that ran on the given node, most recent first.
+    pub fn get_node_jobs(&self, node: &str) -> Vec<&SlurmJob> {
+        let mut jobs: Vec<&SlurmJob> = self
+            .finished_jobs
+            .values()
+            .filter(|j| expand_hostlist(&j.node_list).iter().any(|n| n == node))
+            .collect();
+        jobs.sort_by(|a, b| b.end.cmp(&a.end));
+        jobs
+    }
+
+    /// Individual nodes allocated to the selected job.
+    pub fn get_selected_nodes(&self) -> Vec<String> {
+        self.selected_job()
+            .map(|job| expand_hostlist(&job.node_list))
+            .unwrap_or_default()
+    }
+
+    pub fn get_job(&self, job_id: &str) -> Option<&SlurmJob> {
+        self.raw_slurm_output.iter().find(|j| j.job_id == job_id)
+    }
+
+    pub fn cancel_jobs(&mut self, job_ids: Vec<String>) {
+        if job_ids.is_empty() {
+            return;
+        }
+        self.cancelling = true;
+        self.cancel_targets = job_ids.clone();
+        self.job_ctrl_sender
+            .send(JobControlMessage::CancelJob(job_ids))
+            .unwrap();
+    }
+
+    /// Cancel the selected (or marked) jobs along with everything waiting on them, which would
+    /// otherwise sit in the queue as DependencyNeverSatisfied.
+    pub fn on_cancel_with_dependents(&mut self) {
+        let mut job_ids = self.get_action_ids();
+        for job_id in job_ids.clone() {
+            for dependent in self.dependency_graph.dependents(&job_id) {
+                if !job_ids.contains(&dependent) {
+                    job_ids.push(dependent);
+                }
+            }
+        }
+        // only jobs still in the queue can be cancelled
+        job_ids.retain(|id| self.get_job(id).is_none_or(|j| !j.is_finished()));
+        self.cancel_jobs(job_ids);
     }
 
     pub fn get_job_script(&mut self) {
-        let current_job = self.slurm_jobs.items.get(self.selected_index);
+        let current_job = self.selected_job();
         self.job_script.items = match current_job {
             Some(job) => job.job_script.clone(),
             None => vec!["No job script found".to_string()],
         };
     }
 
+    pub fn get_error_lines(&mut self) {
+        let current_job = self.selected_job().cloned();
+        let job_key = current_job
+            .as_ref()
+            .map(|j| (j.job_id.clone(), j.state.clone()));
+        // only re-read the file when the selection changes, or the selected job fails
+        if job_key == self.error_lines_job {
+            return;
+        }
+        self.error_lines_job = job_key;
+        self.error_lines = match &current_job {
+            Some(job) if job.is_finished() && job.state != "CD" => {
+                let contents = read_tail(&job.stderr_path(), 64 * 1024).unwrap_or_default();
+                let mut lines: Vec<String> = contents
+                    .lines()
+                    .filter(|l| is_error_line(l))
+                    .map(|l| l.trim().to_string())
+                    .collect();
+                lines.drain(..lines.len().saturating_sub(3));
+                lines
+            }
+            _ => Vec::new(),
+        };
+    }
+
+    /// Running jobs that live metrics should be polled for: the selected job and any pinned ones.
+    pub fn get_stat_jobs(&self) -> Vec<String> {
+        let selected = self.selected_job().map(|j| j.job_id.clone());
+        let mut jobs: Vec<String> = self
+            .raw_slurm_output
+            .iter()
+            .filter(|j| j.state == "R")
+            .filter(|j| self.pinned.contains(&j.job_id) || Some(&j.job_id) == selected.as_ref())
+            .map(|j| j.job_id.clone())
+            .collect();
+        jobs.sort();
+        jobs
+    }
+
     // TODO: this function is to go now§
     pub fn get_output_file_path(&mut self) -> Option<PathBuf> {
-        // check if stdout is an existing file
-        let current_job = &self.slurm_jobs.items.get(self.selected_index);
-        if let Some(job) = current_job {
-            if let Some(stdout) = &job.stdout {
-                return Some(PathBuf::from(stdout));
-            } else {
-                return Some(PathBuf::from(format!(
-                    "{}/slurm-{}.out",
-                    job.work_dir.clone(),
-                    job.job_id.clone()
-                )));
-            };
-        }
-        None
+        self.selected_job().map(|job| job.stdout_path())
     }
 
     /// Handles the tick event of the terminal.
@@ -429,6 +1636,10 @@ impl App {
 
     pub fn on_up(&mut self) {
         match self.focus {
+            Focus::JobList if self.array_matrix.is_some() => {
+                let columns = self.array_matrix.as_ref().map_or(1, |m| m.columns);
+                self.move_matrix_cursor(-(columns as isize));
+            }
             Focus::JobList => {
                 self.slurm_jobs.previous();
                 if self.selected_index > 0 {
@@ -450,6 +1661,11 @@ impl App {
                             self.output_line_index = self.output_line_index.saturating_sub(1);
                         }
                     }
+                    RightPanelFocus::Hooks => self.hook_log.previous(),
+                    RightPanelFocus::Efficiency
+                    | RightPanelFocus::Metrics
+                    | RightPanelFocus::Dependencies
+                    | RightPanelFocus::Nodes => {}
                 }
                 // now this should just scroll up on the output text
             }
@@ -458,9 +1674,13 @@ impl App {
 
     pub fn on_down(&mut self) {
         match self.focus {
+            Focus::JobList if self.array_matrix.is_some() => {
+                let columns = self.array_matrix.as_ref().map_or(1, |m| m.columns);
+                self.move_matrix_cursor(columns as isize);
+            }
             Focus::JobList => {
                 self.slurm_jobs.next();
-                if self.selected_index < self.slurm_jobs.len() - 1 {
+                if self.selected_index + 1 < self.slurm_jobs.len() {
                     self.selected_index = self.selected_index.saturating_add(1);
                     // self.get_output_file_contents();
                 }
@@ -479,6 +1699,12 @@ impl App {
                             self.output_line_index = self.output_line_index.saturating_add(1);
                         }
                     }
+                    RightPanelFocus::Hooks if !self.hook_log.is_empty() => self.hook_log.next(),
+                    RightPanelFocus::Efficiency
+                    | RightPanelFocus::Metrics
+                    | RightPanelFocus::Dependencies
+                    | RightPanelFocus::Nodes
+                    | RightPanelFocus::Hooks => {}
                 }
                 // now this should just scroll down on the output text
                 // self.job_output.next();
@@ -533,6 +1759,11 @@ impl App {
                                 .saturating_sub(10),
                         ));
                     }
+                    RightPanelFocus::Efficiency
+                    | RightPanelFocus::Metrics
+                    | RightPanelFocus::Dependencies
+                    | RightPanelFocus::Nodes
+                    | RightPanelFocus::Hooks => {}
                 }
                 self.output_line_index = self.output_line_index.saturating_sub(10);
 
@@ -552,33 +1783,36 @@ impl App {
     pub fn on_shift_down(&mut self) {
         match self.focus {
             Focus::JobList => {
-                if self.selected_index < self.slurm_jobs.len() - 10 {
-                    self.selected_index = self.selected_index.saturating_add(10);
-                } else {
-                    self.selected_index = self.slurm_jobs.len() - 1;
-                }
+                // a filter can leave fewer than 10 jobs, or none
+                self.selected_index = self
+                    .selected_index
+                    .saturating_add(10)
+                    .min(self.slurm_jobs.len().saturating_sub(1));
                 self.slurm_jobs.state.select(Some(self.selected_index));
             }
             Focus::Output => {
                 match self.right_panel_focus {
                     RightPanelFocus::Output => {
-                        if self.output_line_index < self.job_output.len() - 10 {
-                            self.output_line_index = self.output_line_index.saturating_add(10);
-                        } else {
-                            self.output_line_index = self.job_output.len() - 1;
-                        }
+                        self.output_line_index = self
+                            .output_line_index
+                            .saturating_add(10)
+                            .min(self.job_output.len().saturating_sub(1));
                         // select the new line
                         self.job_output.state.select(Some(self.output_line_index));
                     }
                     RightPanelFocus::JobScript => {
-                        if self.output_line_index < self.job_script.len() - 10 {
-                            self.output_line_index = self.output_line_index.saturating_add(10);
-                        } else {
-                            self.output_line_index = self.job_script.len() - 1;
-                        }
+                        self.output_line_index = self
+                            .output_line_index
+                            .saturating_add(10)
+                            .min(self.job_script.len().saturating_sub(1));
                         // select the new line
                         self.job_script.state.select(Some(self.output_line_index));
                     }
+                    RightPanelFocus::Efficiency
+                    | RightPanelFocus::Metrics
+                    | RightPanelFocus::Dependencies
+                    | RightPanelFocus::Nodes
+                    | RightPanelFocus::Hooks => {}
                 }
             }
         }
@@ -600,6 +1834,11 @@ impl App {
                     self.output_line_index = 0;
                     self.job_script.top();
                 }
+                RightPanelFocus::Hooks => self.hook_log.top(),
+                RightPanelFocus::Efficiency
+                | RightPanelFocus::Metrics
+                | RightPanelFocus::Dependencies
+                | RightPanelFocus::Nodes => {}
             },
         }
     }
@@ -609,11 +1848,212 @@ impl App {
                 self.right_panel_focus = RightPanelFocus::JobScript;
             }
             RightPanelFocus::JobScript => {
+                self.right_panel_focus = RightPanelFocus::Efficiency;
+            }
+            RightPanelFocus::Efficiency => {
+                self.right_panel_focus = RightPanelFocus::Metrics;
+            }
+            RightPanelFocus::Metrics => {
+                self.right_panel_focus = RightPanelFocus::Dependencies;
+            }
+            RightPanelFocus::Dependencies => {
+                self.right_panel_focus = RightPanelFocus::Nodes;
+            }
+            RightPanelFocus::Nodes => {
+                self.right_panel_focus = RightPanelFocus::Hooks;
+            }
+            RightPanelFocus::Hooks => {
                 self.right_panel_focus = RightPanelFocus::Output;
             }
         }
         self.output_line_index = 1;
     }
+    /// Watch or stop watching the selected job, or the whole array from an array row.
+    pub fn on_w(&mut self) {
+        let id = match (
+            &self.array_matrix,
+            self.slurm_jobs.items.get(self.selected_index),
+        ) {
+            (Some(_), _) => self.selected_job().map(|j| j.job_id.clone()),
+            (None, row) => row.map(|r| r.expand_key()),
+        };
+        if let Some(id) = id {
+            if !self.watched.remove(&id) {
+                self.watched.insert(id);
+            }
+        }
+    }
+
+    pub fn is_watched(&self, job: &SlurmJob) -> bool {
+        self.watched.contains(&job.job_id)
+            || job
+                .array_job_id()
+                .is_some_and(|id| self.watched.contains(id))
+    }
+
+    pub fn on_p(&mut self) {
+        if let Some(job_id) = self.selected_job().map(|j| j.job_id.clone()) {
+            if !self.pinned.remove(&job_id) {
+                self.pinned.insert(job_id);
+            }
+        }
+    }
+
+    /// Expand or collapse the steps of the selected job, collapsing from a step row moves the
+    /// selection back up to its parent.
+    pub fn on_enter(&mut self) {
+        let Some(row) = self.slurm_jobs.items.get(self.selected_index) else {
+            return;
+        };
+        let key = row.expand_key();
+        if !self.expanded.remove(&key) {
+            self.expanded.insert(key);
+        }
+        // the table is reselected on the job when the step row goes
+        self.build_job_table();
+    }
+
+    /// Open the task matrix for the selected array, or close it if it is already open.
+    pub fn on_m(&mut self) {
+        if self.array_matrix.take().is_some() {
+            return;
+        }
+        let Some(row) = self.slurm_jobs.items.get(self.selected_index) else {
+            return;
+        };
+        let array_job_id = match row {
+            JobRow::Array(array) => Some(array.array_job_id.clone()),
+            row => row
+                .job()
+                .and_then(|j| j.array_job_id())
+                .map(|id| id.to_string()),
+        };
+        if let Some(array_job_id) = array_job_id {
+            // start on the selected task if a task row was selected
+            let cursor = self
+                .get_matrix_tasks(&array_job_id)
+                .iter()
+                .position(|t| Some(t.job_id.as_str()) == row.job().map(|j| j.job_id.as_str()))
+                .unwrap_or(0);
+            self.array_matrix = Some(ArrayMatrix {
+                array_job_id,
+                cursor,
+                columns: 1,
+            });
+        }
+    }
+
+    pub fn move_matrix_cursor(&mut self, delta: isize) {
+        let Some(matrix) = &self.array_matrix else {
+            return;
+        };
+        let len = self.get_matrix_tasks(&matrix.array_job_id).len();
+        if let Some(matrix) = &mut self.array_matrix {
+            matrix.cursor = matrix
+                .cursor
+                .saturating_add_signed(delta)
+                .min(len.saturating_sub(1));
+        }
+    }
+
+    /// The array the selected row belongs to, if any.
+    fn selected_array_job_id(&self) -> Option<String> {
+        match self.slurm_jobs.items.get(self.selected_index) {
+            _ if self.array_matrix.is_some() => {
+                self.array_matrix.as_ref().map(|m| m.array_job_id.clone())
+            }
+            Some(JobRow::Array(array)) => Some(array.array_job_id.clone()),
+            Some(row) => row
+                .job()
+                .and_then(|j| j.array_job_id())
+                .map(|id| id.to_string()),
+            None => None,
+        }
+    }
+
+    fn resubmit_tasks(&mut self, array_job_id: &str, indices: &[u64]) {
+        let tasks = self.get_matrix_tasks(array_job_id);
+        let Some(template) = tasks.first().map(|&t| t.clone()) else {
+            return;
+        };
+        let indices = compress_ranges(indices);
+        self.requeueing = true;
+        self.requeue_target = format!("{} tasks {}", array_job_id, indices);
+        self.job_ctrl_sender
+            .send(JobControlMessage::ResubmitTasks(
+                Box::new(template),
+                indices,
+            ))
+            .unwrap();
+    }
+
+    /// Resubmit the tasks of the selected array that failed, timed out, hit a node failure or
+    /// ran out of memory.
+    pub fn on_resubmit_failed(&mut self) {
+        let Some(array_job_id) = self.selected_array_job_id() else {
+            self.status_message = Some("Select an array to resubmit its failed tasks".to_string());
+            return;
+        };
+        let failed: Vec<u64> = self
+            .get_matrix_tasks(&array_job_id)
+            .iter()
+            .filter(|t| ["F", "TO", "NF", "OOM"].contains(&t.state.as_str()))
+            .filter_map(|t| t.array_task_id())
+            .collect();
+        if failed.is_empty() {
+            self.status_message = Some(format!("Array {} has no failed tasks", array_job_id));
+            return;
+        }
+        self.resubmit_tasks(&array_job_id, &failed);
+    }
+
+    /// Whether there are marks and all of them are tasks of arrays, which 'r' resubmits rather
+    /// than acting on the selected job.
+    fn marks_are_array_tasks(&self) -> bool {
+        !self.marked.is_empty()
+            && self.marked.iter().all(|id| {
+                id.split_once('_')
+                    .is_some_and(|(_, task)| task.parse::<u64>().is_ok())
+            })
+    }
+
+    /// Resubmit just the marked tasks of the selected array.
+    pub fn on_resubmit_marked(&mut self) {
+        let Some(array_job_id) = self.selected_array_job_id() else {
+            self.status_message = Some("Select an array to resubmit its marked tasks".to_string());
+            return;
+        };
+        let marked: Vec<u64> = self
+            .get_matrix_tasks(&array_job_id)
+            .iter()
+            .filter(|t| self.marked.contains(&t.job_id))
+            .filter_map(|t| t.array_task_id())
+            .collect();
+        if marked.is_empty() {
+            self.status_message = Some(format!("No tasks of array {} are marked", array_job_id));
+            return;
+        }
+        self.resubmit_tasks(&array_job_id, &marked);
+    }
+
+    /// Mark or unmark the selected job so that it is acted on together with the other marked
+    /// jobs, e.g. to cancel a handful of the tasks of an array.
+    pub fn on_space(&mut self) {
+        let job_id = match (
+            &self.array_matrix,
+            self.slurm_jobs.items.get(self.selected_index),
+        ) {
+            (Some(_), _) => self.selected_job().map(|j| j.job_id.clone()),
+            (None, Some(JobRow::Job(job))) => Some(job.job_id.clone()),
+            _ => None,
+        };
+        if let Some(job_id) = job_id {
+            if !self.marked.remove(&job_id) {
+                self.marked.insert(job_id);
+            }
+        }
+    }
+
     pub fn on_f(&mut self) {
         self.running_only = !self.running_only;
         self.build_job_table();
@@ -622,20 +2062,106 @@ impl App {
     pub fn on_b(&mut self) {
         match self.focus {
             Focus::JobList => {
-                self.selected_index = self.slurm_jobs.len() - 1;
+                self.selected_index = self.slurm_jobs.len().saturating_sub(1);
                 self.slurm_jobs.bottom();
                 // self.get_output_file_contents();
             }
             Focus::Output => match self.right_panel_focus {
                 RightPanelFocus::Output => {
-                    self.output_line_index = self.job_output.len() - 1;
+                    self.output_line_index = self.job_output.len().saturating_sub(1);
                     self.job_output.bottom();
                 }
                 RightPanelFocus::JobScript => {
-                    self.output_line_index = self.job_script.len() - 1;
+                    self.output_line_index = self.job_script.len().saturating_sub(1);
                     self.job_script.bottom();
                 }
+                RightPanelFocus::Hooks if !self.hook_log.is_empty() => self.hook_log.bottom(),
+                RightPanelFocus::Efficiency
+                | RightPanelFocus::Metrics
+                | RightPanelFocus::Dependencies
+                | RightPanelFocus::Nodes
+                | RightPanelFocus::Hooks => {}
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_lines() {
+        assert!(is_error_line(
+            "slurmstepd: error: Detected 1 oom-kill event(s)"
+        ));
+        assert!(is_error_line("RuntimeError: CUDA out of memory"));
+        assert!(is_error_line("Traceback (most recent call last):"));
+        assert!(!is_error_line("no room left in the zoom bloom filter"));
+        assert!(!is_error_line("epoch 3 loss 0.12"));
+    }
+
+    #[test]
+    fn empty_and_short_lists_stay_in_bounds() {
+        let mut table: StatefulTable<u8> = StatefulTable::with_items(Vec::new());
+        table.next();
+        table.bottom();
+        table.previous();
+        assert_eq!(table.state.selected(), Some(0));
+        let mut table = StatefulTable::with_items(vec![1]);
+        table.next();
+        assert_eq!(table.state.selected(), Some(0));
+        let mut table = StatefulTable::with_items(vec![1, 2, 3]);
+        table.bottom();
+        table.next();
+        assert_eq!(table.state.selected(), Some(2));
+
+        let mut list: StatefulList<u8> = StatefulList::with_items(Vec::new());
+        list.top();
+        list.previous();
+        list.next();
+        list.bottom();
+        assert_eq!(list.state.selected(), Some(0));
+        let mut list = StatefulList::with_items(vec![1, 2]);
+        list.top();
+        list.previous();
+        assert_eq!(list.state.selected(), Some(1));
+        list.next();
+        assert_eq!(list.state.selected(), Some(0));
+    }
+
+    fn array(states: &[&str]) -> ArrayJob {
+        ArrayJob {
+            array_job_id: "100".to_string(),
+            tasks: states
+                .iter()
+                .enumerate()
+                .map(|(i, state)| SlurmJob {
+                    job_id: format!("100_{}", i),
+                    state: state.to_string(),
+                    ..SlurmJob::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn array_state() {
+        assert_eq!(array(&["CD", "CD"]).state(), "CD");
+        assert_eq!(array(&["CA", "CA"]).state(), "CA");
+        assert_eq!(array(&["CD", "CA"]).state(), "CA");
+        assert_eq!(array(&["CD", "CA", "TO"]).state(), "F");
+        assert_eq!(array(&["CD", "F", "R"]).state(), "R");
+        assert_eq!(array(&["CA", "PD"]).state(), "PD");
+    }
+
+    #[test]
+    fn array_state_summary() {
+        assert_eq!(
+            array(&["PD", "CD", "R", "F", "CD", "CA"]).state_summary(),
+            "2 CD, 1 F, 1 CA, 1 R, 1 PD"
+        );
+        assert_eq!(array(&["CA", "CA"]).state_summary(), "2 CA");
+        assert_eq!(array(&[]).state_summary(), "");
+    }
+}
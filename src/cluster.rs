@@ -0,0 +1,377 @@
+use std::{collections::BTreeMap, thread, time::Duration};
+
+use crossbeam::{
+    channel::{tick, unbounded, Receiver, RecvError, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+
+enum ClusterWatcherMessage {
+    Active(bool),
+}
+
+/// A node as listed by `sinfo -N`, once for every partition it belongs to.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeInfo {
+    pub name: String,
+    pub partition: String,
+    // sinfo marks the default partition with a trailing '*'
+    pub default_partition: bool,
+    // long state with slurm's flag suffixes, e.g. "mixed", "drained*", "idle~"
+    pub state: String,
+    pub cpus_alloc: u64,
+    pub cpus_idle: u64,
+    pub cpus_other: u64,
+    pub cpus_total: u64,
+    pub gres: String,
+    pub gres_used: String,
+    pub time_limit: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeState {
+    Idle,
+    Alloc,
+    Mixed,
+    Down,
+    Other,
+}
+
+impl NodeInfo {
+    pub fn node_state(&self) -> NodeState {
+        // a trailing '*' means the node isn't responding, whatever its state says
+        if self.state.ends_with('*') {
+            return NodeState::Down;
+        }
+        match self
+            .state
+            .trim_end_matches(|c: char| !c.is_ascii_alphabetic())
+        {
+            "idle" => NodeState::Idle,
+            "allocated" | "completing" => NodeState::Alloc,
+            "mixed" => NodeState::Mixed,
+            "down" | "drained" | "draining" | "drng" | "fail" | "failing" | "inval"
+            | "not_responding" => NodeState::Down,
+            _ => NodeState::Other,
+        }
+    }
+}
+
+/// Totals over the nodes of a partition.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PartitionSummary {
+    pub name: String,
+    pub default: bool,
+    pub idle: usize,
+    pub alloc: usize,
+    pub mixed: usize,
+    pub down: usize,
+    pub other: usize,
+    pub cpus_idle: u64,
+    pub cpus_total: u64,
+    // (gres type, free, total), e.g. ("gpu:a100", 6, 16)
+    pub gres: Vec<(String, u64, u64)>,
+    pub time_limit: String,
+}
+
+impl PartitionSummary {
+    /// Free generic resources, e.g. "gpu:a100 6/16".
+    pub fn gres_summary(&self) -> String {
+        self.gres
+            .iter()
+            .map(|(name, free, total)| format!("{} {}/{}", name, free, total))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+}
+
+/// Group the nodes by partition, in the order sinfo lists the partitions.
+pub fn summarize(nodes: &[NodeInfo]) -> Vec<PartitionSummary> {
+    let mut partitions: Vec<PartitionSummary> = Vec::new();
+    let mut gres: Vec<BTreeMap<String, (u64, u64)>> = Vec::new();
+    for node in nodes {
+        let i = match partitions.iter().position(|p| p.name == node.partition) {
+            Some(i) => i,
+            None => {
+                partitions.push(PartitionSummary {
+                    name: node.partition.clone(),
+                    default: node.default_partition,
+                    time_limit: node.time_limit.clone(),
+                    ..Default::default()
+                });
+                gres.push(BTreeMap::new());
+                partitions.len() - 1
+            }
+        };
+        let partition = &mut partitions[i];
+        match node.node_state() {
+            NodeState::Idle => partition.idle += 1,
+            NodeState::Alloc => partition.alloc += 1,
+            NodeState::Mixed => partition.mixed += 1,
+            NodeState::Down => partition.down += 1,
+            NodeState::Other => partition.other += 1,
+        }
+        let down = node.node_state() == NodeState::Down;
+        if !down {
+            partition.cpus_idle += node.cpus_idle;
+        }
+        partition.cpus_total += node.cpus_total;
+        let used = parse_gres(&node.gres_used);
+        for (name, total) in parse_gres(&node.gres) {
+            let used = used
+                .iter()
+                .find(|(n, _)| *n == name)
+                .map_or(0, |(_, used)| *used);
+            let entry = gres[i].entry(name).or_default();
+            if !down {
+                entry.0 += total.saturating_sub(used);
+            }
+            entry.1 += total;
+        }
+    }
+    for (partition, gres) in partitions.iter_mut().zip(gres) {
+        partition.gres = gres
+            .into_iter()
+            .map(|(name, (free, total))| (name, free, total))
+            .collect();
+    }
+    partitions
+}
+
+/// Parse a gres string, e.g. "gpu:a100:4(S:0-1),shard:8" into [("gpu:a100", 4), ("shard", 8)].
+pub fn parse_gres(gres: &str) -> Vec<(String, u64)> {
+    if ["", "(null)", "N/A"].contains(&gres.trim()) {
+        return Vec::new();
+    }
+    // commas also separate the socket list inside the brackets, drop those first
+    let mut stripped = String::new();
+    let mut depth = 0;
+    for c in gres.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 => stripped.push(c),
+            _ => {}
+        }
+    }
+    stripped
+        .split(',')
+        .filter(|g| !g.is_empty())
+        .map(|g| match g.rsplit_once(':') {
+            Some((name, count)) if count.parse::<u64>().is_ok() => {
+                (name.to_string(), count.parse().unwrap_or(0))
+            }
+            _ => (g.to_string(), 1),
+        })
+        .collect()
+}
+
+/// The nodes in `sinfo -N --Format` output, fields separated by "##".
+fn parse_sinfo(output: &str) -> Vec<NodeInfo> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts = line.split("##").map(|s| s.trim()).collect::<Vec<&str>>();
+            if parts.len() < 8 {
+                return None;
+            }
+            // allocated/idle/other/total
+            let cpus = parts[3]
+                .split('/')
+                .map(|c| c.parse().unwrap_or(0))
+                .collect::<Vec<u64>>();
+            let cpu = |i: usize| cpus.get(i).copied().unwrap_or(0);
+            Some(NodeInfo {
+                name: parts[0].to_string(),
+                partition: parts[1].trim_end_matches('*').to_string(),
+                default_partition: parts[1].ends_with('*'),
+                state: parts[2].to_string(),
+                cpus_alloc: cpu(0),
+                cpus_idle: cpu(1),
+                cpus_other: cpu(2),
+                cpus_total: cpu(3),
+                gres: parts[4].replace("(null)", ""),
+                gres_used: parts[5].replace("(null)", ""),
+                time_limit: parts[6].to_string(),
+                reason: match parts[7] {
+                    "none" => String::new(),
+                    reason => reason.to_string(),
+                },
+            })
+        })
+        .collect()
+}
+
+struct ClusterWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<ClusterWatcherMessage>,
+    interval: Duration,
+    active: bool,
+}
+
+impl ClusterWatcher {
+    fn new(
+        app: Sender<AppMessage>,
+        receiver: Receiver<ClusterWatcherMessage>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            app,
+            receiver,
+            interval,
+            active: false,
+        }
+    }
+
+    fn run(&mut self) -> Result<(), RecvError> {
+        let ticker = tick(self.interval);
+        loop {
+            select! {
+                recv(self.receiver) -> msg => {
+                    match msg? {
+                        ClusterWatcherMessage::Active(active) => {
+                            self.active = active;
+                            self.refresh();
+                        }
+                    }
+                }
+                recv(ticker) -> _ => self.refresh(),
+            }
+        }
+    }
+
+    fn refresh(&self) {
+        // only bother slurmctld while the cluster view is open
+        if !self.active {
+            return;
+        }
+        // the --json schema has changed with most slurm releases, --Format has not.  If sinfo
+        // fails the last nodes are kept until the next tick
+        let Some(output) = std::process::Command::new("sinfo")
+            .args(["--noheader", "-N"])
+            .arg(
+                "--Format=NodeList:##,Partition:##,StateLong:##,CPUsState:##,Gres:##,GresUsed:##,\
+Time:##,Reason:##",
+            )
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+        else {
+            return;
+        };
+        let nodes = parse_sinfo(&String::from_utf8_lossy(&output.stdout));
+        self.app.send(AppMessage::ClusterInfo(nodes)).unwrap();
+    }
+}
+
+#[derive(Debug)]
+pub struct ClusterWatcherHandle {
+    sender: Sender<ClusterWatcherMessage>,
+    active: bool,
+}
+
+impl ClusterWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, interval: Duration) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = ClusterWatcher::new(app, receiver, interval);
+        thread::spawn(move || actor.run());
+        Self {
+            sender,
+            active: false,
+        }
+    }
+
+    /// Poll sinfo only while the cluster view is shown.
+    pub fn set_active(&mut self, active: bool) {
+        if self.active != active {
+            self.active = active;
+            self.sender
+                .send(ClusterWatcherMessage::Active(active))
+                .unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(partition: &str, state: &str, cpus: &str, gres: &str, gres_used: &str) -> String {
+        format!(
+            "node01##{}##{}##{}##{}##{}##1-00:00:00##none",
+            partition, state, cpus, gres, gres_used
+        )
+    }
+
+    #[test]
+    fn gres() {
+        assert_eq!(
+            parse_gres("gpu:a100:4(S:0-1),shard:8"),
+            [("gpu:a100".to_string(), 4), ("shard".to_string(), 8)]
+        );
+        assert_eq!(parse_gres("gpu:2"), [("gpu".to_string(), 2)]);
+        assert_eq!(parse_gres("gpu"), [("gpu".to_string(), 1)]);
+        assert_eq!(
+            parse_gres("gpu:a100:2(IDX:0,3),mps:0"),
+            [("gpu:a100".to_string(), 2), ("mps".to_string(), 0)]
+        );
+        assert!(parse_gres("(null)").is_empty());
+        assert!(parse_gres("").is_empty());
+    }
+
+    #[test]
+    fn node_states() {
+        let state = |state: &str| {
+            NodeInfo {
+                state: state.to_string(),
+                ..NodeInfo::default()
+            }
+            .node_state()
+        };
+        assert_eq!(state("idle"), NodeState::Idle);
+        assert_eq!(state("idle~"), NodeState::Idle);
+        assert_eq!(state("idle*"), NodeState::Down);
+        assert_eq!(state("mixed"), NodeState::Mixed);
+        assert_eq!(state("allocated+"), NodeState::Alloc);
+        assert_eq!(state("drained"), NodeState::Down);
+        assert_eq!(state("reserved"), NodeState::Other);
+    }
+
+    #[test]
+    fn down_nodes_have_nothing_free() {
+        let output = [
+            node(
+                "gpu*",
+                "mixed",
+                "16/48/0/64",
+                "gpu:a100:4(S:0-1)",
+                "gpu:a100:1(IDX:0)",
+            ),
+            node(
+                "gpu*",
+                "drained",
+                "0/64/0/64",
+                "gpu:a100:4(S:0-1)",
+                "gpu:a100:0(IDX:N/A)",
+            ),
+            node("cpu", "idle", "0/32/0/32", "(null)", "(null)"),
+            "short##line".to_string(),
+        ]
+        .join("\n");
+        let nodes = parse_sinfo(&output);
+        assert_eq!(nodes.len(), 3);
+        assert_eq!(nodes[2].gres, "");
+        let partitions = summarize(&nodes);
+        assert_eq!(partitions.len(), 2);
+        let gpu = &partitions[0];
+        assert_eq!((gpu.name.as_str(), gpu.default), ("gpu", true));
+        assert_eq!((gpu.mixed, gpu.down), (1, 1));
+        assert_eq!((gpu.cpus_idle, gpu.cpus_total), (48, 128));
+        assert_eq!(gpu.gres_summary(), "gpu:a100 3/8");
+        let cpu = &partitions[1];
+        assert_eq!((cpu.idle, cpu.cpus_idle, cpu.cpus_total), (1, 32, 32));
+        assert!(cpu.gres.is_empty());
+    }
+}
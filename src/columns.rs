@@ -0,0 +1,440 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+use ratatui::layout::Constraint;
+use serde::{Deserialize, Serialize};
+
+use crate::slurm::{format_duration, parse_duration, seconds_until, JobStep, SlurmJob};
+
+/// A column the job list can show, any of the fields of a job or one worked out from them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Column {
+    Id,
+    State,
+    Name,
+    Partition,
+    Account,
+    Qos,
+    Submit,
+    Start,
+    End,
+    // time between submission and start, up to now for pending jobs
+    Wait,
+    Elapsed,
+    TimeLimit,
+    // elapsed as a percentage of the time limit
+    Percent,
+    Nodes,
+    NodeCount,
+    Cpus,
+    ReqMem,
+    ExitCode,
+    DerivedExitCode,
+    WorkDir,
+    Reason,
+    Dependency,
+    // how long ago a running job last wrote to its stdout or stderr
+    LastOutput,
+}
+
+impl Column {
+    pub const ALL: [Column; 23] = [
+        Column::Id,
+        Column::State,
+        Column::Name,
+        Column::Partition,
+        Column::Account,
+        Column::Qos,
+        Column::Submit,
+        Column::Start,
+        Column::End,
+        Column::Wait,
+        Column::Elapsed,
+        Column::TimeLimit,
+        Column::Percent,
+        Column::Nodes,
+        Column::NodeCount,
+        Column::Cpus,
+        Column::ReqMem,
+        Column::ExitCode,
+        Column::DerivedExitCode,
+        Column::WorkDir,
+        Column::Reason,
+        Column::Dependency,
+        Column::LastOutput,
+    ];
+
+    /// The name used in the config file and the `:columns` command.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Id => "id",
+            Column::State => "state",
+            Column::Name => "name",
+            Column::Partition => "partition",
+            Column::Account => "account",
+            Column::Qos => "qos",
+            Column::Submit => "submit",
+            Column::Start => "start",
+            Column::End => "end",
+            Column::Wait => "wait",
+            Column::Elapsed => "elapsed",
+            Column::TimeLimit => "time_limit",
+            Column::Percent => "percent",
+            Column::Nodes => "nodes",
+            Column::NodeCount => "node_count",
+            Column::Cpus => "cpus",
+            Column::ReqMem => "req_mem",
+            Column::ExitCode => "exit_code",
+            Column::DerivedExitCode => "derived_exit_code",
+            Column::WorkDir => "work_dir",
+            Column::Reason => "reason",
+            Column::Dependency => "dependency",
+            Column::LastOutput => "last_output",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Column::Id => "ID",
+            Column::State => "ST",
+            Column::Name => "NAME",
+            Column::Partition => "PARTITION",
+            Column::Account => "ACCOUNT",
+            Column::Qos => "QOS",
+            Column::Submit => "SUBMIT",
+            Column::Start => "START",
+            Column::End => "END",
+            Column::Wait => "WAIT",
+            Column::Elapsed => "ELAPSED",
+            Column::TimeLimit => "LIMIT",
+            Column::Percent => "%",
+            Column::Nodes => "NODES",
+            Column::NodeCount => "NN",
+            Column::Cpus => "CPUS",
+            Column::ReqMem => "MEM",
+            Column::ExitCode => "EXIT",
+            Column::DerivedExitCode => "DEXIT",
+            Column::WorkDir => "WORK DIR",
+            Column::Reason => "REASON",
+            Column::Dependency => "DEPENDENCY",
+            Column::LastOutput => "OUTPUT",
+        }
+    }
+
+    /// Characters the column needs, the free-text columns get any width left over on top.
+    pub fn min_width(&self) -> u16 {
+        match self {
+            Column::Id => 16,
+            Column::State => 3,
+            Column::Percent | Column::NodeCount => 4,
+            Column::Cpus | Column::ExitCode | Column::DerivedExitCode => 5,
+            Column::ReqMem | Column::Qos => 7,
+            Column::Wait | Column::Elapsed | Column::TimeLimit => 11,
+            Column::Submit | Column::Start | Column::End => 19,
+            Column::Partition | Column::Account => 10,
+            Column::Name | Column::Nodes | Column::Reason => 12,
+            Column::Dependency | Column::LastOutput => 14,
+            Column::WorkDir => 20,
+        }
+    }
+
+    pub fn width(&self) -> Constraint {
+        match self {
+            Column::Name
+            | Column::Partition
+            | Column::Account
+            | Column::Nodes
+            | Column::Reason
+            | Column::Dependency
+            | Column::WorkDir => Constraint::Min(self.min_width()),
+            _ => Constraint::Length(self.min_width()),
+        }
+    }
+
+    /// The text of the column for a job.  The id, state and name are drawn by the job list itself
+    /// as they carry the tree and highlighting, and the last output as it isn't part of the job.
+    pub fn value(&self, job: &SlurmJob) -> String {
+        match self {
+            Column::Id => job.job_id.clone(),
+            Column::State => job.state.clone(),
+            Column::Name => job.job_name.clone(),
+            Column::Partition => job.partition.clone(),
+            Column::Account => job.account.clone(),
+            Column::Qos => job.qos.clone(),
+            Column::Submit => job.submit.clone(),
+            Column::Start => job.start.clone(),
+            Column::End => job.end.clone(),
+            Column::Wait => wait_seconds(job).map(format_duration).unwrap_or_default(),
+            Column::Elapsed => job.elapsed_time.clone(),
+            Column::TimeLimit => job.time_limit.clone(),
+            Column::Percent => percent_complete(job)
+                .map(|p| format!("{:.0}", p))
+                .unwrap_or_default(),
+            Column::Nodes => job.node_list.clone(),
+            Column::NodeCount => job.alloc_nodes.clone(),
+            Column::Cpus => job.alloc_cpus.clone(),
+            Column::ReqMem => job.req_mem.clone(),
+            Column::ExitCode => job.exit_code.clone(),
+            Column::DerivedExitCode => job.derived_exit_code.clone(),
+            Column::WorkDir => job.work_dir.clone(),
+            Column::Reason => job.reason.clone(),
+            Column::Dependency => job.dependency.clone(),
+            Column::LastOutput => String::new(),
+        }
+    }
+
+    /// The text of the column for a step, empty for the columns steps don't have.
+    pub fn step_value(&self, step: &JobStep) -> String {
+        match self {
+            Column::Id => step.step_id.clone(),
+            Column::State => step.state.clone(),
+            Column::Name => step.step_name.clone(),
+            Column::Elapsed => step.elapsed_time.clone(),
+            Column::Nodes => step.node_list.clone(),
+            Column::Cpus => step.alloc_cpus.clone(),
+            Column::ExitCode => step.exit_code.clone(),
+            _ => String::new(),
+        }
+    }
+
+    /// Whether every task of an array has the same value, so the array row can show it.
+    pub fn shared_by_array(&self) -> bool {
+        matches!(
+            self,
+            Column::Partition
+                | Column::Account
+                | Column::Qos
+                | Column::Submit
+                | Column::TimeLimit
+                | Column::Cpus
+                | Column::ReqMem
+                | Column::WorkDir
+                | Column::Dependency
+        )
+    }
+
+    /// Order two jobs by the column.  Ids and numbers compare numerically, and jobs without a
+    /// value, e.g. the start of a pending job, sort after the rest either way round.  The output
+    /// times aren't part of the jobs, `last_output` has them by job id.
+    pub fn compare(
+        &self,
+        a: &SlurmJob,
+        b: &SlurmJob,
+        descending: bool,
+        last_output: &HashMap<String, SystemTime>,
+    ) -> Ordering {
+        compare_values(
+            self.sort_value(a, last_output),
+            self.sort_value(b, last_output),
+            descending,
+        )
+    }
+
+    fn sort_value(
+        &self,
+        job: &SlurmJob,
+        last_output: &HashMap<String, SystemTime>,
+    ) -> Option<SortValue> {
+        let number = |value: Option<f64>| value.map(SortValue::Number);
+        match self {
+            Column::Id => Some(SortValue::Id(job.sort_key())),
+            Column::Wait => number(wait_seconds(job)),
+            Column::Elapsed => number(parse_duration(&job.elapsed_time)),
+            Column::TimeLimit => number(parse_duration(&job.time_limit)),
+            Column::Percent => number(percent_complete(job)),
+            Column::NodeCount => number(job.alloc_nodes.parse().ok()),
+            Column::Cpus => number(job.alloc_cpus.parse().ok()),
+            Column::ReqMem => number(job.req_mem_bytes()),
+            // the longest quiet first, like the wait and elapsed times
+            Column::LastOutput => last_output.get(&job.job_id).copied().map(SortValue::Time),
+            _ => {
+                let value = self.value(job);
+                // slurm's placeholders for times that haven't happened
+                match value.as_str() {
+                    "" | "Unknown" | "None" | "N/A" => None,
+                    _ => Some(SortValue::Text(value.to_lowercase())),
+                }
+            }
+        }
+    }
+}
+
+/// Order two values with a missing one after the other either way round.
+fn compare_values<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    let ordering = match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => return a.is_none().cmp(&b.is_none()),
+    };
+    match descending {
+        true => ordering.reverse(),
+        false => ordering,
+    }
+}
+
+// what a column sorts on, a column always gives the same kind
+#[derive(Debug, PartialEq)]
+enum SortValue {
+    Id((u64, Option<u64>)),
+    Number(f64),
+    Text(String),
+    Time(SystemTime),
+}
+
+impl Eq for SortValue {}
+
+impl PartialOrd for SortValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (SortValue::Id(a), SortValue::Id(b)) => a.cmp(b),
+            (SortValue::Number(a), SortValue::Number(b)) => a.total_cmp(b),
+            (SortValue::Text(a), SortValue::Text(b)) => a.cmp(b),
+            (SortValue::Time(a), SortValue::Time(b)) => a.cmp(b),
+            _ => Ordering::Equal,
+        }
+    }
+}
+
+fn wait_seconds(job: &SlurmJob) -> Option<f64> {
+    let submitted = seconds_until(&job.submit)?;
+    let started = match job.state.as_str() {
+        "PD" => 0,
+        _ => seconds_until(&job.start)?,
+    };
+    Some((started - submitted).max(0) as f64)
+}
+
+fn percent_complete(job: &SlurmJob) -> Option<f64> {
+    let elapsed = parse_duration(&job.elapsed_time)?;
+    let limit = parse_duration(&job.time_limit).filter(|l| *l > 0.)?;
+    Some((elapsed / limit * 100.).min(100.))
+}
+
+impl fmt::Display for Column {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Column::ALL
+            .iter()
+            .find(|c| c.name() == name.trim().to_lowercase())
+            .copied()
+            .ok_or_else(|| {
+                let names = Column::ALL.map(|c| c.name()).join(", ");
+                format!("{}: unknown column, expected one of {}", name, names)
+            })
+    }
+}
+
+/// Which column the job list is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sort {
+    pub column: Column,
+    #[serde(default)]
+    pub descending: bool,
+}
+
+impl Sort {
+    pub fn compare(
+        &self,
+        a: &SlurmJob,
+        b: &SlurmJob,
+        last_output: &HashMap<String, SystemTime>,
+    ) -> Ordering {
+        self.column.compare(a, b, self.descending, last_output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn job(job_id: &str, start: &str) -> SlurmJob {
+        SlurmJob {
+            job_id: job_id.to_string(),
+            start: start.to_string(),
+            ..SlurmJob::default()
+        }
+    }
+
+    fn sorted(column: Column, descending: bool, jobs: &[SlurmJob]) -> Vec<String> {
+        sorted_with(column, descending, jobs, &HashMap::new())
+    }
+
+    fn sorted_with(
+        column: Column,
+        descending: bool,
+        jobs: &[SlurmJob],
+        last_output: &HashMap<String, SystemTime>,
+    ) -> Vec<String> {
+        let sort = Sort { column, descending };
+        let mut jobs = jobs.to_vec();
+        jobs.sort_by(|a, b| sort.compare(a, b, last_output));
+        jobs.into_iter().map(|j| j.job_id).collect()
+    }
+
+    #[test]
+    fn ids_sort_numerically() {
+        let jobs = [
+            job("1000", ""),
+            job("999", ""),
+            job("123_10", ""),
+            job("123_2", ""),
+            job("123", ""),
+        ];
+        assert_eq!(
+            sorted(Column::Id, false, &jobs),
+            ["123", "123_2", "123_10", "999", "1000"]
+        );
+        assert_eq!(
+            sorted(Column::Id, true, &jobs),
+            ["1000", "999", "123_10", "123_2", "123"]
+        );
+    }
+
+    #[test]
+    fn missing_values_go_last_either_way() {
+        let jobs = [
+            job("1", "Unknown"),
+            job("2", "2024-03-02T00:00:00"),
+            job("3", ""),
+            job("4", "2024-03-01T00:00:00"),
+        ];
+        assert_eq!(sorted(Column::Start, false, &jobs), ["4", "2", "1", "3"]);
+        assert_eq!(sorted(Column::Start, true, &jobs), ["2", "4", "1", "3"]);
+    }
+
+    #[test]
+    fn last_output_sorts_by_time() {
+        let now = SystemTime::now();
+        let last_output = HashMap::from([
+            ("1".to_string(), now),
+            ("2".to_string(), now - Duration::from_secs(3600)),
+            ("4".to_string(), now - Duration::from_secs(60)),
+        ]);
+        let jobs = [job("1", ""), job("2", ""), job("3", ""), job("4", "")];
+        assert_eq!(
+            sorted_with(Column::LastOutput, false, &jobs, &last_output),
+            ["2", "4", "1", "3"]
+        );
+        assert_eq!(
+            sorted_with(Column::LastOutput, true, &jobs, &last_output),
+            ["1", "4", "2", "3"]
+        );
+    }
+}
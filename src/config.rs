@@ -0,0 +1,136 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use toml_edit::DocumentMut;
+
+use crate::columns::{Column, Sort};
+use crate::hooks::Hook;
+use crate::notification::NotifyConfig;
+use crate::watchdog::{StallConfig, WatchdogConfig};
+
+/// Settings kept between runs in `~/.config/swatch/config.toml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // the job list's columns, left to right
+    pub columns: Vec<Column>,
+    // the order the job watcher lists the jobs in when unset, which is by id
+    pub sort: Option<Sort>,
+    pub notify: NotifyConfig,
+    // commands run when jobs change state
+    pub hooks: Vec<Hook>,
+    pub watchdog: WatchdogConfig,
+    // when a running job's output has been quiet long enough to be marked stalled
+    pub stall: StallConfig,
+}
+
+// the part of the config swatch writes back, the rest is only ever edited by hand
+#[derive(Serialize)]
+struct Saved<'a> {
+    columns: &'a [Column],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<Sort>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            columns: vec![Column::Id, Column::State, Column::Name],
+            sort: None,
+            notify: NotifyConfig::default(),
+            hooks: Vec::new(),
+            watchdog: WatchdogConfig::default(),
+            stall: StallConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Where the config lives, under $XDG_CONFIG_HOME if it is set.
+    pub fn path() -> Option<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("swatch").join("config.toml"))
+    }
+
+    /// Read the config, falling back to the defaults if there isn't one.  A config that doesn't
+    /// parse is an error rather than being silently replaced on the next save.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|p| p.is_file()) else {
+            return Ok(Self::default());
+        };
+        let contents = std::fs::read_to_string(&path)?;
+        let config: Self =
+            toml::from_str(&contents).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        for rule in &config.watchdog.rules {
+            rule.check()
+                .map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        }
+        Ok(config)
+    }
+
+    /// Write the columns and sort back to the config, the only settings changed from within swatch.
+    /// Everything else in the file, comments included, is left as it was.
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path().ok_or_else(|| anyhow::anyhow!("HOME is not set"))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        std::fs::write(&path, self.update(&contents)?)?;
+        Ok(())
+    }
+
+    /// `contents` with its columns and sort replaced by this config's.
+    fn update(&self, contents: &str) -> Result<String> {
+        let mut doc = contents
+            .parse::<DocumentMut>()
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        let saved = toml_edit::ser::to_document(&Saved {
+            columns: &self.columns,
+            sort: self.sort,
+        })?;
+        for key in ["columns", "sort"] {
+            match saved.get(key) {
+                Some(item) => {
+                    doc.insert(key, item.clone());
+                }
+                None => {
+                    doc.remove(key);
+                }
+            }
+        }
+        Ok(doc.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_keeps_the_rest_of_the_file() {
+        let contents = "# my columns\ncolumns = [\"id\"]\n\n[sort]\ncolumn = \"id\"\n\n# ping me\n[notify]\nwatched_only = true\n\n[[hooks]]\ncommand = \"echo\"\n";
+        let config = Config {
+            columns: vec![Column::Id, Column::Name],
+            sort: None,
+            ..Config::default()
+        };
+        let updated = config.update(contents).unwrap();
+        assert!(updated.contains("# ping me"));
+        assert!(updated.contains("command = \"echo\""));
+        assert!(!updated.contains("[sort]"));
+        let reloaded: Config = toml::from_str(&updated).unwrap();
+        assert_eq!(reloaded.columns, config.columns);
+        assert_eq!(reloaded.sort, None);
+        assert!(reloaded.notify.watched_only);
+        assert_eq!(config.update("").unwrap(), "columns = [\"id\", \"name\"]\n");
+    }
+}
@@ -0,0 +1,75 @@
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{at, never, Receiver};
+
+// scrolling through the job list shouldn't trigger a slurm call per key press
+pub const MIN_REFRESH_GAP: Duration = Duration::from_secs(2);
+
+/// Spaces out refreshes asked for by the user, e.g. an sstat or scontrol call per key press while
+/// scrolling through the job list.  A refresh asked for too soon after the last one is held back
+/// until the gap has passed rather than dropped, so the latest request is always served.
+#[derive(Debug)]
+pub struct Debounce {
+    gap: Duration,
+    last_refresh: Option<Instant>,
+    pending: bool,
+}
+
+impl Debounce {
+    pub fn new(gap: Duration) -> Self {
+        Self {
+            gap,
+            last_refresh: None,
+            pending: false,
+        }
+    }
+
+    /// Ask for a refresh, true if it can run now.  Otherwise it is pending until [`Self::timer`]
+    /// fires.
+    pub fn request(&mut self) -> bool {
+        match self.last_refresh {
+            Some(t) if t.elapsed() < self.gap => {
+                self.pending = true;
+                false
+            }
+            _ => true,
+        }
+    }
+
+    /// Record a refresh, which serves any pending request.
+    pub fn refreshed(&mut self) {
+        self.last_refresh = Some(Instant::now());
+        self.pending = false;
+    }
+
+    /// Fires when a pending refresh is due, never if there isn't one.
+    pub fn timer(&self) -> Receiver<Instant> {
+        match (self.pending, self.last_refresh) {
+            (true, Some(t)) => at(t + self.gap),
+            _ => never(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_request_too_soon_is_held_not_dropped() {
+        let mut debounce = Debounce::new(Duration::from_millis(50));
+        assert!(debounce.request());
+        debounce.refreshed();
+        assert!(debounce.timer().try_recv().is_err());
+        assert!(!debounce.request());
+        assert!(debounce
+            .timer()
+            .recv_timeout(Duration::from_secs(1))
+            .is_ok());
+        debounce.refreshed();
+        assert!(debounce
+            .timer()
+            .recv_timeout(Duration::from_millis(100))
+            .is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::slurm::SlurmJob;
+
+/// One condition of a job's --dependency, e.g. "afterok:123(unfulfilled)".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub kind: String,
+    pub job_id: String,
+    // slurm appends the state of each condition in squeue/scontrol, e.g. "unfulfilled", "failed"
+    pub status: Option<String>,
+}
+
+/// Parse a slurm dependency string, e.g. "afterok:123_4(unfulfilled),afterany:456:457+10".
+pub fn parse_dependency(dependency: &str) -> Vec<Dependency> {
+    let mut dependencies = Vec::new();
+    if ["", "(null)", "None"].contains(&dependency.trim()) {
+        return dependencies;
+    }
+    // ',' means all of the conditions must hold and '?' any of them, the graph treats both alike
+    for condition in dependency.split([',', '?']) {
+        let (condition, status) = match condition.split_once('(') {
+            Some((condition, status)) => {
+                (condition, Some(status.trim_end_matches(')').to_string()))
+            }
+            None => (condition, None),
+        };
+        let mut parts = condition.split(':');
+        let kind = parts.next().unwrap_or_default().to_string();
+        for job_id in parts {
+            // "after:123+10" waits 10 minutes after 123 starts
+            let job_id = job_id.split('+').next().unwrap_or_default();
+            if !job_id.is_empty() {
+                dependencies.push(Dependency {
+                    kind: kind.clone(),
+                    job_id: job_id.to_string(),
+                    status: status.clone(),
+                });
+            }
+        }
+    }
+    dependencies
+}
+
+/// A line of the rendered dependency tree.
+#[derive(Debug, Clone)]
+pub struct TreeLine {
+    // box drawing prefix, e.g. "│  ├─ "
+    pub prefix: String,
+    pub job_id: String,
+    // the condition linking the job to its parent in the tree, None for roots
+    pub dependency: Option<Dependency>,
+}
+
+/// DAG of the user's jobs, with an edge from each job to the jobs that depend on it.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+    dependents: HashMap<String, Vec<(String, Dependency)>>,
+    dependencies: HashMap<String, Vec<Dependency>>,
+}
+
+impl DependencyGraph {
+    pub fn new(jobs: &[SlurmJob]) -> Self {
+        let mut graph = DependencyGraph::default();
+        for job in jobs {
+            let dependencies = parse_dependency(&job.dependency);
+            for dependency in &dependencies {
+                graph
+                    .dependents
+                    .entry(dependency.job_id.clone())
+                    .or_default()
+                    .push((job.job_id.clone(), dependency.clone()));
+            }
+            if !dependencies.is_empty() {
+                graph.dependencies.insert(job.job_id.clone(), dependencies);
+            }
+        }
+        graph
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dependencies.is_empty()
+    }
+
+    /// Every job that directly or indirectly depends on `job_id`, in breadth first order.
+    pub fn dependents(&self, job_id: &str) -> Vec<String> {
+        let mut seen: HashSet<&str> = HashSet::from([job_id]);
+        let mut queue = vec![job_id];
+        let mut dependents = Vec::new();
+        while let Some(id) = queue.pop() {
+            for (dependent, _) in self.dependents.get(id).into_iter().flatten() {
+                if seen.insert(dependent) {
+                    dependents.push(dependent.clone());
+                    queue.insert(0, dependent);
+                }
+            }
+        }
+        dependents
+    }
+
+    /// Render the graph as a forest rooted at the jobs that others depend on but which have no
+    /// dependencies of their own.  A job depending on several others appears under each.
+    pub fn tree(&self) -> Vec<TreeLine> {
+        let mut roots: Vec<&String> = self
+            .dependents
+            .keys()
+            .filter(|id| !self.dependencies.contains_key(*id))
+            .collect();
+        roots.sort_by_key(|id| job_id_sort_key(id));
+        let mut lines = Vec::new();
+        for root in roots {
+            lines.push(TreeLine {
+                prefix: String::new(),
+                job_id: root.clone(),
+                dependency: None,
+            });
+            let mut path = vec![root.as_str()];
+            self.add_children(root, "", &mut path, &mut lines);
+        }
+        lines
+    }
+
+    fn add_children<'a>(
+        &'a self,
+        job_id: &str,
+        indent: &str,
+        path: &mut Vec<&'a str>,
+        lines: &mut Vec<TreeLine>,
+    ) {
+        let Some(children) = self.dependents.get(job_id) else {
+            return;
+        };
+        let mut children = children.iter().collect::<Vec<_>>();
+        children.sort_by_key(|(id, _)| job_id_sort_key(id));
+        for (i, (child, dependency)) in children.iter().enumerate() {
+            let last = i == children.len() - 1;
+            lines.push(TreeLine {
+                prefix: format!("{}{}", indent, if last { "└─ " } else { "├─ " }),
+                job_id: child.clone(),
+                dependency: Some(dependency.clone()),
+            });
+            // slurm rejects circular dependencies, but don't loop forever if one slips through
+            if path.contains(&child.as_str()) {
+                continue;
+            }
+            path.push(child);
+            let indent = format!("{}{}", indent, if last { "   " } else { "│  " });
+            self.add_children(child, &indent, path, lines);
+            path.pop();
+        }
+    }
+}
+
+fn job_id_sort_key(job_id: &str) -> (u64, u64) {
+    let (base, task) = job_id.split_once('_').unwrap_or((job_id, "0"));
+    (base.parse().unwrap_or(u64::MAX), task.parse().unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(job_id: &str, dependency: &str) -> SlurmJob {
+        SlurmJob {
+            job_id: job_id.to_string(),
+            dependency: dependency.to_string(),
+            ..SlurmJob::default()
+        }
+    }
+
+    fn ids(dependencies: &[Dependency]) -> Vec<(&str, &str)> {
+        dependencies
+            .iter()
+            .map(|d| (d.kind.as_str(), d.job_id.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_conditions() {
+        assert!(parse_dependency("").is_empty());
+        assert!(parse_dependency("(null)").is_empty());
+        let dependencies = parse_dependency("afterok:123_4(unfulfilled),afterany:456:457+10");
+        assert_eq!(
+            ids(&dependencies),
+            [
+                ("afterok", "123_4"),
+                ("afterany", "456"),
+                ("afterany", "457")
+            ]
+        );
+        assert_eq!(dependencies[0].status.as_deref(), Some("unfulfilled"));
+        assert_eq!(dependencies[1].status, None);
+        assert_eq!(
+            ids(&parse_dependency("afterok:1?afternotok:2+5?singleton")),
+            [("afterok", "1"), ("afternotok", "2")]
+        );
+        assert_eq!(
+            ids(&parse_dependency("aftercorr:100_[1-3](failed)")),
+            [("aftercorr", "100_[1-3]")]
+        );
+    }
+
+    #[test]
+    fn dependents_are_transitive() {
+        let graph = DependencyGraph::new(&[
+            job("1", ""),
+            job("2", "afterok:1"),
+            job("3", "afterany:1?afterok:2"),
+            job("4", "afterok:3"),
+            job("5", "afterok:9"),
+        ]);
+        assert_eq!(graph.dependents("1"), ["2", "3", "4"]);
+        assert_eq!(graph.dependents("3"), ["4"]);
+        assert!(graph.dependents("4").is_empty());
+        assert_eq!(graph.dependents("9"), ["5"]);
+    }
+
+    #[test]
+    fn cycles_end() {
+        let graph = DependencyGraph::new(&[
+            job("1", "afterok:3"),
+            job("2", "afterok:1"),
+            job("3", "afterok:2"),
+            job("4", "afterok:5:6"),
+            job("6", "afterok:4"),
+        ]);
+        assert_eq!(graph.dependents("1"), ["2", "3"]);
+        // 5 is the only job without dependencies, the cycle under it is cut where it repeats
+        let tree = graph
+            .tree()
+            .iter()
+            .map(|l| format!("{}{}", l.prefix, l.job_id))
+            .collect::<Vec<_>>();
+        assert_eq!(tree, ["5", "└─ 4", "   └─ 6", "      └─ 4"]);
+    }
+
+    #[test]
+    fn tree_lists_a_job_under_each_dependency() {
+        let graph = DependencyGraph::new(&[
+            job("1", ""),
+            job("2", ""),
+            job("10", "afterok:1:2"),
+            job("3", "afterok:1"),
+        ]);
+        let tree = graph
+            .tree()
+            .iter()
+            .map(|l| format!("{}{}", l.prefix, l.job_id))
+            .collect::<Vec<_>>();
+        assert_eq!(tree, ["1", "├─ 3", "└─ 10", "2", "└─ 10"]);
+    }
+}
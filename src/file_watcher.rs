@@ -61,11 +61,8 @@ impl FileWatcher {
 
         let mut watcher = recommended_watcher(move |res: notify::Result<notify::Event>| {
             let event = res.unwrap();
-            match event.kind {
-                notify::EventKind::Modify(ModifyKind::Data(_)) => {
-                    watch_sender.send(event.paths).unwrap();
-                }
-                _ => {}
+            if let notify::EventKind::Modify(ModifyKind::Data(_)) = event.kind {
+                watch_sender.send(event.paths).unwrap();
             }
         })
         .unwrap();
@@ -83,7 +80,7 @@ impl FileWatcher {
 
 
                         if let Some(p) = &self.file_path {
-                            watcher.unwatch(p).expect(format!("Failed to unwatch {:?}", p).as_str());
+                            watcher.unwatch(p).unwrap_or_else(|_| panic!("Failed to unwatch {:?}", p));
                             self.file_path = None;
                         }
                         if let Some(p) = file_path {
@@ -91,7 +88,7 @@ impl FileWatcher {
                             match res {
                                 Ok(_) => {
                                     self.file_path = Some(p.clone());
-                                    let i = self.interval.clone();
+                                    let i = self.interval;
                                     thread::spawn(move || FileReader::new(_content_sender, _watch_receiver, p, i).run());
                                 }
                                 Err(e) => {
@@ -106,7 +103,7 @@ impl FileWatcher {
                 }
                 recv(watch_receiver) -> _ => {_watch_sender.send(()).unwrap();}
                 recv(_content_receiver) -> msg => {
-                    self.app.send(AppMessage::OutputFile(msg.unwrap().map_err(|e| FileWatcherError::File(e)))).unwrap();
+                    self.app.send(AppMessage::OutputFile(msg.unwrap().map_err(FileWatcherError::File))).unwrap();
             }
             }
         }
@@ -139,12 +136,59 @@ impl FileWatcherHandle {
     }
 }
 
+/// Read at most the last `max_bytes` of a file, used where only the end of a (potentially huge)
+/// output file is of interest.
+pub fn read_tail(file_path: &Path, max_bytes: u64) -> io::Result<String> {
+    let mut f = File::open(file_path)?;
+    let len = f.metadata()?.len();
+    f.seek(io::SeekFrom::Start(len.saturating_sub(max_bytes)))?;
+    let mut buf = Vec::new();
+    f.read_to_end(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
+
+/// Reads what has been appended to a file since it was last read.
+#[derive(Debug)]
+pub struct Tail {
+    file_path: PathBuf,
+    pos: u64,
+}
+
+impl Tail {
+    pub fn new(file_path: PathBuf) -> Self {
+        Self { file_path, pos: 0 }
+    }
+
+    /// Start near the end of the file, no more than `max_bytes` back from it.
+    pub fn from_end(file_path: PathBuf, max_bytes: u64) -> Self {
+        let len = std::fs::metadata(&file_path).map_or(0, |m| m.len());
+        Self {
+            file_path,
+            pos: len.saturating_sub(max_bytes),
+        }
+    }
+
+    /// What has been written since the last read, and whether the file was truncated or replaced
+    /// since, in which case it is read again from the start.
+    pub fn read(&mut self) -> io::Result<(String, bool)> {
+        let mut f = File::open(&self.file_path)?;
+        let truncated = f.metadata()?.len() < self.pos;
+        if truncated {
+            self.pos = 0;
+        }
+        f.seek(io::SeekFrom::Start(self.pos))?;
+        let mut buf = Vec::new();
+        // advance the position by the number of bytes read from the file
+        self.pos += f.read_to_end(&mut buf)? as u64;
+        Ok((String::from_utf8_lossy(&buf).to_string(), truncated))
+    }
+}
+
 struct FileReader {
     content_sender: Sender<io::Result<String>>,
     receiver: Receiver<()>,
-    file_path: PathBuf,
     interval: Duration,
-    pos: u64,
+    tail: Tail,
     content: String,
 }
 
@@ -158,9 +202,8 @@ impl FileReader {
         Self {
             content_sender,
             receiver,
-            file_path,
             interval,
-            pos: 0,
+            tail: Tail::new(file_path),
             content: "".to_string(),
         }
     }
@@ -179,15 +222,32 @@ impl FileReader {
     }
 
     fn update(&mut self) -> Result<(), SendError<io::Result<String>>> {
-        let s = File::open(&self.file_path).and_then(|mut f| {
-            // update the position in the file, so we just
-            self.pos = f.seek(io::SeekFrom::Start(self.pos))?;
-            // advance the position by the number of bytes read from the file
-            self.pos += f.read_to_string(&mut self.content)? as u64;
-            Ok(self.content.clone())
+        let s = self.tail.read().map(|(new, truncated)| {
+            if truncated {
+                self.content.clear();
+            }
+            self.content.push_str(&new);
+            self.content.clone()
         });
         // send the entire self.content string with the new stuff back on the channel
 
         self.content_sender.send(s)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_reports_truncation() {
+        let path = std::env::temp_dir().join(format!("swatch-tail-{}", std::process::id()));
+        std::fs::write(&path, "one\ntw").unwrap();
+        let mut tail = Tail::new(path.clone());
+        assert_eq!(tail.read().unwrap(), ("one\ntw".to_string(), false));
+        assert_eq!(tail.read().unwrap(), (String::new(), false));
+        std::fs::write(&path, "x\n").unwrap();
+        assert_eq!(tail.read().unwrap(), ("x\n".to_string(), true));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,344 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::job_watcher::parse_time_period;
+use crate::query::{glob_match, parse_job_id_range};
+use crate::slurm::{expand_hostlist, parse_duration, state_code, SlurmJob};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    State,
+    Partition,
+    Account,
+    Qos,
+    Name,
+    Dir,
+    Reason,
+    Id,
+    Node,
+    Elapsed,
+    Limit,
+}
+
+impl Field {
+    fn parse(key: &str) -> Result<Self, String> {
+        Ok(match key.to_lowercase().as_str() {
+            "state" | "st" => Field::State,
+            "partition" | "part" => Field::Partition,
+            "account" => Field::Account,
+            "qos" => Field::Qos,
+            "name" => Field::Name,
+            "dir" | "workdir" => Field::Dir,
+            "reason" => Field::Reason,
+            "id" => Field::Id,
+            "node" | "nodes" => Field::Node,
+            "elapsed" => Field::Elapsed,
+            "limit" | "timelimit" => Field::Limit,
+            _ => {
+                return Err(format!(
+                    "{}: unknown field, expected state, partition, account, qos, name, dir, \
+                    reason, id, node, elapsed or limit",
+                    key
+                ))
+            }
+        })
+    }
+
+    fn text(&self, job: &SlurmJob) -> Vec<String> {
+        match self {
+            Field::State => vec![job.state.clone()],
+            Field::Partition => vec![job.partition.clone()],
+            Field::Account => vec![job.account.clone()],
+            Field::Qos => vec![job.qos.clone()],
+            Field::Name => vec![job.job_name.clone()],
+            Field::Dir => vec![job.work_dir.clone()],
+            Field::Reason => vec![job.reason.clone()],
+            Field::Id => vec![job.job_id.clone()],
+            Field::Node => expand_hostlist(&job.node_list),
+            Field::Elapsed => vec![job.elapsed_time.clone()],
+            Field::Limit => vec![job.time_limit.clone()],
+        }
+    }
+
+    // seconds for the durations
+    fn number(&self, job: &SlurmJob) -> Option<f64> {
+        match self {
+            Field::Elapsed => parse_duration(&job.elapsed_time),
+            Field::Limit => parse_duration(&job.time_limit),
+            Field::Id => Some(job.sort_key().0 as f64),
+            _ => None,
+        }
+    }
+
+    fn parse_number(&self, value: &str) -> Result<f64, String> {
+        match self {
+            // either "2h", "90m" or slurm's own "1-00:00:00"
+            Field::Elapsed | Field::Limit => match value.contains(':') {
+                true => {
+                    parse_duration(value).ok_or_else(|| format!("{}: expected a duration", value))
+                }
+                false => parse_time_period(value).map(|d| d.as_secs_f64()),
+            },
+            Field::Id => value
+                .parse::<u64>()
+                .map(|id| id as f64)
+                .map_err(|_| format!("{}: expected a job id", value)),
+            _ => Err("only elapsed, limit and id can be compared with < and >".to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Compare {
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    // equal to one of the values, which may have wildcards, e.g. "state:F,TO"
+    OneOf(Vec<String>),
+    // contains the value, or matches it if it has wildcards, e.g. name~"sweep_*"
+    Like(String),
+    Compare(Compare, f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    negated: bool,
+    field: Field,
+    condition: Condition,
+}
+
+impl Term {
+    fn matches(&self, job: &SlurmJob) -> bool {
+        let matched = match &self.condition {
+            Condition::OneOf(values) => match self.field {
+                Field::Id => values.iter().any(|v| id_matches(v, job)),
+                _ => self.field.text(job).iter().any(|text| {
+                    values
+                        .iter()
+                        .any(|v| glob_match(&v.to_lowercase(), &text.to_lowercase()))
+                }),
+            },
+            Condition::Like(value) => {
+                let value = value.to_lowercase();
+                self.field.text(job).iter().any(|text| {
+                    let text = text.to_lowercase();
+                    match value.contains(['*', '?']) {
+                        true => glob_match(&value, &text),
+                        false => text.contains(&value),
+                    }
+                })
+            }
+            Condition::Compare(compare, value) => match self.field.number(job) {
+                Some(number) => match compare {
+                    Compare::Greater => number > *value,
+                    Compare::GreaterEq => number >= *value,
+                    Compare::Less => number < *value,
+                    Compare::LessEq => number <= *value,
+                },
+                None => false,
+            },
+        };
+        matched != self.negated
+    }
+}
+
+// an id is either the job's own, e.g. "123_4", its array's, e.g. "123", or a range of them
+fn id_matches(value: &str, job: &SlurmJob) -> bool {
+    if value == job.job_id || Some(value) == job.array_job_id() {
+        return true;
+    }
+    match parse_job_id_range(value) {
+        Ok((start, end)) => (start..=end).contains(&job.sort_key().0),
+        Err(_) => false,
+    }
+}
+
+/// A filter over the job table typed in with `:filter`, e.g.
+/// `state:F,TO partition:gpu name~"sweep_*" elapsed>2h node:gpu0[1-4]`.
+///
+/// Terms are separated by spaces and all of them have to match.  `field:a,b` matches any of the
+/// values, `field~text` matches text anywhere in the field, and elapsed, limit and id can be
+/// compared with `<`, `<=`, `>` and `>=`.  A leading '-' negates a term and a bare word is looked
+/// for in the job name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct JobFilter {
+    source: String,
+    terms: Vec<Term>,
+}
+
+impl JobFilter {
+    pub fn matches(&self, job: &SlurmJob) -> bool {
+        self.terms.iter().all(|term| term.matches(job))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+// split on whitespace, except inside double quotes, which are dropped
+fn tokenize(filter: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut quoted = false;
+    for c in filter.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !token.is_empty() {
+                    tokens.push(std::mem::take(&mut token));
+                }
+            }
+            c => token.push(c),
+        }
+    }
+    if quoted {
+        return Err("unclosed quote".to_string());
+    }
+    if !token.is_empty() {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_term(token: &str) -> Result<Term, String> {
+    let (negated, token) = match token.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() => (true, rest),
+        _ => (false, token),
+    };
+    let Some(at) = token.find([':', '~', '<', '>']) else {
+        return Ok(Term {
+            negated,
+            field: Field::Name,
+            condition: Condition::Like(token.to_string()),
+        });
+    };
+    let field = Field::parse(&token[..at])?;
+    let rest = &token[at..];
+    let (operator, value) = [">=", "<=", ":", "~", ">", "<"]
+        .iter()
+        .find_map(|op| rest.strip_prefix(op).map(|value| (*op, value)))
+        .unwrap_or(("", rest));
+    if value.is_empty() {
+        return Err(format!("{}: missing a value", token));
+    }
+    let condition = match operator {
+        ":" => Condition::OneOf(match field {
+            // hostlists have their own commas, e.g. "gpu[01,03]"
+            Field::Node => expand_hostlist(value),
+            // "running" finds the jobs listed as "R"
+            Field::State => value.split(',').map(state_code).collect(),
+            // a bad range would otherwise quietly match nothing
+            Field::Id => value
+                .split(',')
+                .map(|v| match v.contains('-') {
+                    true => parse_job_id_range(v).map(|_| v.to_string()),
+                    false => Ok(v.to_string()),
+                })
+                .collect::<Result<Vec<String>, String>>()?,
+            _ => value.split(',').map(|v| v.to_string()).collect(),
+        }),
+        "~" => Condition::Like(value.to_string()),
+        op => {
+            let compare = match op {
+                ">" => Compare::Greater,
+                ">=" => Compare::GreaterEq,
+                "<" => Compare::Less,
+                _ => Compare::LessEq,
+            };
+            Condition::Compare(compare, field.parse_number(value)?)
+        }
+    };
+    Ok(Term {
+        negated,
+        field,
+        condition,
+    })
+}
+
+impl FromStr for JobFilter {
+    type Err = String;
+
+    fn from_str(filter: &str) -> Result<Self, Self::Err> {
+        let terms = tokenize(filter)?
+            .iter()
+            .map(|token| parse_term(token))
+            .collect::<Result<Vec<Term>, String>>()?;
+        Ok(JobFilter {
+            source: filter.trim().to_string(),
+            terms,
+        })
+    }
+}
+
+impl fmt::Display for JobFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(job_id: &str, state: &str, name: &str) -> SlurmJob {
+        SlurmJob {
+            job_id: job_id.to_string(),
+            state: state.to_string(),
+            job_name: name.to_string(),
+            partition: "gpu".to_string(),
+            node_list: "gpu[01-02]".to_string(),
+            elapsed_time: "03:00:00".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn matches(filter: &str, job: &SlurmJob) -> bool {
+        filter.parse::<JobFilter>().unwrap().matches(job)
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let filter: JobFilter = "   ".parse().unwrap();
+        assert!(filter.is_empty());
+        assert!(filter.matches(&job("1", "R", "a")));
+    }
+
+    #[test]
+    fn terms_all_have_to_match() {
+        let sweep = job("100_3", "F", "sweep lr");
+        assert!(matches("state:F,TO partition:gpu", &sweep));
+        assert!(matches("state:failed", &sweep));
+        assert!(!matches("state:R partition:gpu", &sweep));
+        assert!(matches("name~\"sweep l*\" elapsed>2h", &sweep));
+        assert!(matches("elapsed>=03:00:00 elapsed<1d", &sweep));
+        assert!(matches("SWEEP -state:R", &sweep));
+        assert!(matches("node:gpu[02,05] id:100", &sweep));
+        assert!(matches("id:90-110", &sweep));
+        assert!(!matches("-id:100_3", &sweep));
+    }
+
+    #[test]
+    fn bad_filters_are_errors() {
+        for filter in [
+            "nope:1",
+            "state:",
+            "name~\"open",
+            "elapsed>soon",
+            "partition>2",
+            "id:5-3",
+        ] {
+            assert!(filter.parse::<JobFilter>().is_err(), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn a_lone_dash_is_a_name() {
+        assert!(matches("-", &job("1", "R", "a-b")));
+    }
+}
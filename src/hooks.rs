@@ -0,0 +1,344 @@
+use std::fmt;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::{thread, time::Duration, time::Instant};
+
+use chrono::{DateTime, Local};
+use crossbeam::channel::{bounded, unbounded, Receiver, RecvError, Sender};
+use serde::{Deserialize, Serialize};
+
+use crate::app::AppMessage;
+use crate::notification::Transition;
+use crate::query::glob_match;
+use crate::slurm::SlurmJob;
+
+// lines of a hook's output kept to show in the app
+const MAX_OUTPUT_LINES: usize = 200;
+// how long the output is waited for once the hook has exited or been killed
+const READER_GRACE: Duration = Duration::from_secs(1);
+
+fn default_timeout() -> u64 {
+    60
+}
+
+/// A command run when a job changes state, a `[[hooks]]` table in the config, e.g.
+///
+/// ```toml
+/// [[hooks]]
+/// on = ["R->CD"]
+/// job_name = "train_*"
+/// command = "rsync -a $SWATCH_WORK_DIR/results/ ~/results/$SWATCH_JOB_ID/"
+/// ```
+///
+/// The command is run by bash in the job's work dir with the job described by `SWATCH_JOB_ID`,
+/// `SWATCH_JOB_NAME`, `SWATCH_WORK_DIR`, `SWATCH_STDOUT`, `SWATCH_STDERR`, `SWATCH_OLD_STATE`
+/// and `SWATCH_NEW_STATE`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    pub command: String,
+    // the state changes that run the hook, any change if there are none
+    #[serde(default)]
+    pub on: Vec<Transition>,
+    // a pattern the job name has to match, e.g. "train_*"
+    #[serde(default)]
+    pub job_name: Option<String>,
+    // seconds before the hook is killed
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+}
+
+impl Hook {
+    pub fn applies(&self, job: &SlurmJob, old_state: &str) -> bool {
+        (self.on.is_empty() || self.on.iter().any(|t| t.matches(old_state, &job.state)))
+            && self
+                .job_name
+                .as_ref()
+                .is_none_or(|pattern| glob_match(pattern, &job.job_name))
+    }
+}
+
+/// How a hook run ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookOutcome {
+    Exited(i32),
+    // killed by a signal rather than exiting
+    Killed,
+    TimedOut,
+    // bash couldn't be started
+    Failed(String),
+}
+
+impl fmt::Display for HookOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookOutcome::Exited(code) => write!(f, "exit {}", code),
+            HookOutcome::Killed => write!(f, "killed"),
+            HookOutcome::TimedOut => write!(f, "timed out"),
+            HookOutcome::Failed(e) => write!(f, "failed: {}", e),
+        }
+    }
+}
+
+/// A finished run of a hook, with what it printed.
+#[derive(Debug, Clone)]
+pub struct HookRun {
+    pub command: String,
+    pub job_id: String,
+    pub old_state: String,
+    pub new_state: String,
+    pub started: DateTime<Local>,
+    pub duration: Duration,
+    pub outcome: HookOutcome,
+    // stdout then stderr, the last MAX_OUTPUT_LINES lines of them
+    pub output: Vec<String>,
+}
+
+impl HookRun {
+    pub fn succeeded(&self) -> bool {
+        self.outcome == HookOutcome::Exited(0)
+    }
+}
+
+struct HookRunner {
+    app: Sender<AppMessage>,
+    receiver: Receiver<(Hook, SlurmJob, String)>,
+}
+
+#[derive(Debug)]
+pub struct HookRunnerHandle {
+    sender: Sender<(Hook, SlurmJob, String)>,
+}
+
+impl HookRunner {
+    fn new(app: Sender<AppMessage>, receiver: Receiver<(Hook, SlurmJob, String)>) -> Self {
+        Self { app, receiver }
+    }
+
+    fn run(&mut self) -> Result<(), RecvError> {
+        loop {
+            let (hook, job, old_state) = self.receiver.recv()?;
+            // each run gets a thread of its own so a slow hook doesn't hold up the others
+            let app = self.app.clone();
+            thread::spawn(move || {
+                let run = run_hook(&hook, &job, &old_state);
+                let _ = app.send(AppMessage::HookFinished(run));
+            });
+        }
+    }
+}
+
+fn run_hook(hook: &Hook, job: &SlurmJob, old_state: &str) -> HookRun {
+    let started = Local::now();
+    let start = Instant::now();
+    let mut command = Command::new("bash");
+    command
+        .arg("-c")
+        .arg(&hook.command)
+        .env("SWATCH_JOB_ID", &job.job_id)
+        .env("SWATCH_JOB_NAME", &job.job_name)
+        .env("SWATCH_WORK_DIR", &job.work_dir)
+        .env("SWATCH_STDOUT", job.stdout_path())
+        .env("SWATCH_STDERR", job.stderr_path())
+        .env("SWATCH_OLD_STATE", old_state)
+        .env("SWATCH_NEW_STATE", &job.state)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if std::path::Path::new(&job.work_dir).is_dir() {
+        command.current_dir(&job.work_dir);
+    }
+    // a group of its own, so a timeout kills whatever the hook started too
+    command.process_group(0);
+    let (outcome, output) = match command.spawn() {
+        Ok(child) => wait(child, Duration::from_secs(hook.timeout)),
+        Err(e) => (HookOutcome::Failed(e.to_string()), Vec::new()),
+    };
+    HookRun {
+        command: hook.command.clone(),
+        job_id: job.job_id.clone(),
+        old_state: old_state.to_string(),
+        new_state: job.state.clone(),
+        started,
+        duration: start.elapsed(),
+        outcome,
+        output,
+    }
+}
+
+fn wait(mut child: Child, timeout: Duration) -> (HookOutcome, Vec<String>) {
+    // read both pipes as the hook runs, a full pipe would block it
+    let (sender, receiver) = bounded(2);
+    let pipes: [Option<Box<dyn Read + Send>>; 2] = [
+        child
+            .stdout
+            .take()
+            .map(|out| Box::new(out) as Box<dyn Read + Send>),
+        child
+            .stderr
+            .take()
+            .map(|err| Box::new(err) as Box<dyn Read + Send>),
+    ];
+    let mut readers = 0;
+    for (index, pipe) in pipes.into_iter().enumerate() {
+        if let Some(mut pipe) = pipe {
+            let sender = sender.clone();
+            thread::spawn(move || {
+                let mut contents = String::new();
+                let _ = pipe.read_to_string(&mut contents);
+                let _ = sender.send((index, contents));
+            });
+            readers += 1;
+        }
+    }
+    let deadline = Instant::now() + timeout;
+    let outcome = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => {
+                break match status.code() {
+                    Some(code) => HookOutcome::Exited(code),
+                    None => HookOutcome::Killed,
+                }
+            }
+            Ok(None) if Instant::now() >= deadline => {
+                kill_group(&child);
+                let _ = child.kill();
+                let _ = child.wait();
+                break HookOutcome::TimedOut;
+            }
+            Ok(None) => thread::sleep(Duration::from_millis(100)),
+            Err(e) => break HookOutcome::Failed(e.to_string()),
+        }
+    };
+    // something the hook left running in the background can hold the pipes open after it exits,
+    // wait a little for it and then kill it, the pipes close with it
+    let mut contents = Vec::new();
+    let mut reader_deadline = deadline
+        .min(Instant::now() + READER_GRACE)
+        .max(Instant::now());
+    let mut killed = false;
+    while contents.len() < readers {
+        match receiver.recv_deadline(reader_deadline) {
+            Ok(read) => contents.push(read),
+            Err(_) if !killed => {
+                kill_group(&child);
+                killed = true;
+                reader_deadline = Instant::now() + READER_GRACE;
+            }
+            // still open, whatever holds them has left the group, give up on the rest
+            Err(_) => break,
+        }
+    }
+    contents.sort();
+    let mut output = contents
+        .iter()
+        .flat_map(|(_, contents)| contents.lines().map(|l| l.to_string()))
+        .collect::<Vec<String>>();
+    output.drain(..output.len().saturating_sub(MAX_OUTPUT_LINES));
+    (outcome, output)
+}
+
+// kill the process group the hook leads, everything it started included
+fn kill_group(child: &Child) {
+    let _ = Command::new("kill")
+        .args(["-KILL", "--", &format!("-{}", child.id())])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+}
+
+impl HookRunnerHandle {
+    pub fn new(app: Sender<AppMessage>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = HookRunner::new(app, receiver);
+        thread::spawn(move || actor.run());
+
+        Self { sender }
+    }
+
+    pub fn run(&self, hook: Hook, job: SlurmJob, old_state: String) {
+        self.sender.send((hook, job, old_state)).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(name: &str, state: &str) -> SlurmJob {
+        SlurmJob {
+            job_id: "1".to_string(),
+            job_name: name.to_string(),
+            state: state.to_string(),
+            work_dir: "/nonexistent".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn hook(command: &str, on: &[&str], job_name: Option<&str>) -> Hook {
+        Hook {
+            command: command.to_string(),
+            on: on.iter().map(|t| t.parse().unwrap()).collect(),
+            job_name: job_name.map(|n| n.to_string()),
+            timeout: default_timeout(),
+        }
+    }
+
+    #[test]
+    fn applies() {
+        let any = hook("true", &[], None);
+        assert!(any.applies(&job("a", "CD"), "R"));
+        let done = hook("true", &["R->CD", "*->TO"], Some("train_*"));
+        assert!(done.applies(&job("train_1", "CD"), "R"));
+        assert!(done.applies(&job("train_1", "TO"), "PD"));
+        assert!(!done.applies(&job("train_1", "F"), "R"));
+        assert!(!done.applies(&job("train_1", "CD"), "PD"));
+        assert!(!done.applies(&job("eval_1", "CD"), "R"));
+    }
+
+    #[test]
+    fn runs_with_the_job_in_its_environment() {
+        let run = run_hook(
+            &hook(
+                "echo \"$SWATCH_JOB_NAME $SWATCH_OLD_STATE->$SWATCH_NEW_STATE\"; echo err >&2; exit 3",
+                &[],
+                None,
+            ),
+            &job("train_1", "F"),
+            "R",
+        );
+        assert_eq!(run.outcome, HookOutcome::Exited(3));
+        assert_eq!(run.output, ["train_1 R->F", "err"]);
+        assert!(!run.succeeded());
+    }
+
+    fn spawn(command: &str) -> Child {
+        Command::new("bash")
+            .arg("-c")
+            .arg(command)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap()
+    }
+
+    #[test]
+    fn times_out() {
+        let start = Instant::now();
+        let (outcome, output) = wait(spawn("echo started; sleep 30"), Duration::from_millis(200));
+        assert_eq!(outcome, HookOutcome::TimedOut);
+        assert_eq!(output, ["started"]);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn background_children_dont_hold_it_up() {
+        let start = Instant::now();
+        let (outcome, output) = wait(spawn("sleep 30 & echo done"), Duration::from_secs(30));
+        assert_eq!(outcome, HookOutcome::Exited(0));
+        assert_eq!(output, ["done"]);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,118 +1,471 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::{thread, time::Duration};
 
-use crossbeam::channel::{Receiver, Sender};
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use crossbeam::{
+    channel::{tick, unbounded, Receiver, RecvError, Sender},
+    select,
+};
 
 use crate::app::AppMessage;
-use crate::slurm::SlurmJob;
+use crate::dependency::parse_dependency;
+use crate::pending::{parse_sprio, Priority};
+use crate::query::JobQuery;
+use crate::slurm::{expand_ranges, is_finished_state, JobStep, SlurmJob, STATE_CODES};
+
+// the fields asked of sacct with --format, a line with fewer isn't sacct output
+const SACCT_FIELDS: usize = 24;
+
+enum JobWatcherMessage {
+    TimeWindow(TimeWindow),
+    Query(JobQuery),
+}
+
+/// The jobs in `sacct --parsable2` output, with their steps attached to them rather than listed
+/// as jobs of their own.
+fn parse_sacct(output: &str) -> Vec<SlurmJob> {
+    let status_map = HashMap::from(STATE_CODES);
+    let mut job_list: Vec<SlurmJob> = Vec::new();
+    output.lines().skip(1).for_each(|line| {
+        let parts = line.split('|').collect::<Vec<&str>>();
+        // steps (batch, extern, 0, 1, ...) are listed straight after their parent job, attach
+        // them to it rather than listing them as jobs of their own
+        if let Some((parent_id, step_id)) = parts[0].split_once('.') {
+            if let Some(parent) = job_list.iter_mut().rev().find(|j| j.job_id == parent_id) {
+                parent.steps.push(JobStep {
+                    step_id: step_id.to_string(),
+                    step_name: parts[1].to_string(),
+                    state: status_map
+                        .get(parts[7].split_whitespace().next().unwrap_or_default())
+                        .unwrap_or(&parts[7])
+                        .to_string(),
+                    exit_code: parts[13].to_string(),
+                    elapsed_time: parts[11].to_string(),
+                    node_list: parts[12].to_string(),
+                    max_rss: parts[15].to_string(),
+                    total_cpu: parts[17].to_string(),
+                    alloc_cpus: parts[18].to_string(),
+                });
+            }
+            return;
+        }
+        if parts[1] == "_interactive" {
+            return;
+        }
+        let job_id = parts[0].to_string();
+        let job_name = parts[1].to_string();
+        let partition = parts[2].to_string();
+        let account = parts[3].to_string();
+        let submit = parts[4].to_string(); // parse this to datetime
+        let start = parts[5].to_string();
+        let end = parts[6].to_string();
+        let state = status_map
+            .get(parts[7].split_whitespace().next().unwrap())
+            .unwrap_or(&parts[7])
+            .to_string();
+        let work_dir = parts[8].to_string();
+        let reason = parts[9].to_string();
+        let time_limit = parts[10].to_string();
+        let elapsed_time = parts[11].to_string();
+        let node_list = parts[12].to_string();
+        // we don't get stdout from sacct, use best guess for completed jobs, otherwise this
+        // will be filled from squeue later.
+        let (stdout, stderr) = (None, None);
+        // read the job script
+        let job_script = std::fs::read_to_string(format!("{}/{}", work_dir, job_name))
+            .ok()
+            .map(|s| s.lines().map(|s| s.to_string()).collect::<Vec<String>>())
+            .unwrap_or_else(|| vec!["No job script found".to_string()]);
+        let mut job = SlurmJob::new(
+            job_id,
+            job_name,
+            partition,
+            account,
+            state,
+            start,
+            submit,
+            end,
+            reason,
+            work_dir,
+            time_limit,
+            elapsed_time,
+            stdout,
+            stderr,
+            node_list,
+            job_script,
+        );
+        job.exit_code = parts[13].to_string();
+        job.derived_exit_code = parts[14].to_string();
+        job.req_mem = parts[16].to_string();
+        job.total_cpu = parts[17].to_string();
+        job.alloc_cpus = parts[18].to_string();
+        job.alloc_nodes = parts[19].to_string();
+        job.req_tres = parts[20].to_string();
+        job.alloc_tres = parts[21].to_string();
+        job.consumed_energy = parts[22].to_string();
+        job.qos = parts[23].to_string();
+        // tasks of an array that haven't started yet are reported as a single "123_[4-100]"
+        // row, list each of them so they can be grouped with the rest of the array
+        match job.job_id.split_once("_[") {
+            Some((array_job_id, ranges)) => {
+                for index in expand_ranges(ranges.trim_end_matches(']')) {
+                    let mut task = job.clone();
+                    task.job_id = format!("{}_{}", array_job_id, index);
+                    job_list.push(task);
+                }
+            }
+            None => job_list.push(job),
+        }
+    });
+    job_list
+}
+
+/// Where a pending job with `priority` is in the queue of `(id, partitions, priority)` pending
+/// jobs, and how long the queue is.  A job submitted to several partitions is as far along as its
+/// best queue, and is in the queue of each of them.
+fn queue_position(
+    partitions: &str,
+    priority: u64,
+    queue: &[(String, String, u64)],
+) -> Option<(usize, usize)> {
+    partitions
+        .split(',')
+        .map(|partition| {
+            let pending = queue
+                .iter()
+                .filter(|(_, p, _)| p.split(',').any(|p| p == partition));
+            let ahead = pending.clone().filter(|(_, _, p)| *p > priority).count();
+            (ahead + 1, pending.count())
+        })
+        .filter(|(_, pending)| *pending > 0)
+        .min()
+}
+
+/// The Dependency= of each job in `scontrol -o show job` output, by job id.  Array tasks are
+/// under "<array>_<task>", and a pending array's tasks that haven't been split out under the
+/// array's id.
+fn parse_scontrol_dependencies(output: &str) -> HashMap<String, String> {
+    let mut dependencies = HashMap::new();
+    for line in output.lines() {
+        let fields: HashMap<&str, &str> = line
+            .split_whitespace()
+            .filter_map(|field| field.split_once('='))
+            .collect();
+        let Some(dependency) = fields.get("Dependency") else {
+            continue;
+        };
+        let id = match (fields.get("ArrayJobId"), fields.get("ArrayTaskId")) {
+            (Some(array), Some(task)) if task.parse::<u64>().is_ok() => {
+                format!("{}_{}", array, task)
+            }
+            (Some(array), Some(_)) => array.to_string(),
+            _ => match fields.get("JobId") {
+                Some(id) => id.to_string(),
+                None => continue,
+            },
+        };
+        dependencies.insert(id, dependency.to_string());
+    }
+    dependencies
+}
+
+// what a command printed, None if it couldn't be run or failed
+fn command_output(command: &mut std::process::Command) -> Option<String> {
+    let output = command.output().ok().filter(|o| o.status.success())?;
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Which jobs sacct is asked for, by when they were last active.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimeWindow {
+    // up to now, e.g. the last 24 hours
+    Last(Duration),
+    // between two points in time, until now if there is no end
+    Range {
+        since: NaiveDateTime,
+        until: Option<NaiveDateTime>,
+    },
+}
+
+impl TimeWindow {
+    /// The -S and -E options for sacct.
+    fn sacct_args(&self) -> Vec<String> {
+        let format = |time: NaiveDateTime| time.format("%Y-%m-%dT%H:%M:%S").to_string();
+        match self {
+            TimeWindow::Last(duration) => {
+                let duration =
+                    chrono::Duration::from_std(*duration).unwrap_or(chrono::Duration::max_value());
+                // anything further back than the epoch means everything
+                let since = Local::now()
+                    .naive_local()
+                    .checked_sub_signed(duration)
+                    .unwrap_or_default();
+                vec!["-S".to_string(), format(since)]
+            }
+            TimeWindow::Range { since, until } => match until {
+                Some(until) => vec![
+                    "-S".to_string(),
+                    format(*since),
+                    "-E".to_string(),
+                    format(*until),
+                ],
+                None => vec!["-S".to_string(), format(*since)],
+            },
+        }
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let format = |time: &NaiveDateTime| time.format("%Y-%m-%d %H:%M").to_string();
+        match self {
+            TimeWindow::Last(duration) => {
+                write!(f, "last {}", humantime::format_duration(*duration))
+            }
+            TimeWindow::Range { since, until } => match until {
+                Some(until) => write!(f, "{} to {}", format(since), format(until)),
+                None => write!(f, "since {}", format(since)),
+            },
+        }
+    }
+}
+
+/// Parse a duration like "90m", "3d" or "1h 30m", a bare number is a number of hours.
+pub fn parse_time_period(period: &str) -> Result<Duration, String> {
+    if let Ok(hours) = period.trim().parse::<u64>() {
+        return Ok(Duration::from_secs(hours * 3600));
+    }
+    humantime::parse_duration(period.trim()).map_err(|e| format!("{}: {}", period, e))
+}
+
+/// Parse a date, e.g. "2024-03-01", or a date and time, e.g. "2024-03-01T12:00".
+pub fn parse_time(time: &str) -> Result<NaiveDateTime, String> {
+    let time = time.trim();
+    [
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(time, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(time, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+    .ok_or_else(|| format!("{}: expected YYYY-MM-DD[THH:MM[:SS]]", time))
+}
+
+impl FromStr for TimeWindow {
+    type Err = String;
+
+    /// Either a duration, e.g. "3d", or a range of times, e.g. "2024-03-01..2024-03-05", where
+    /// a missing end means up to now.
+    fn from_str(window: &str) -> Result<Self, Self::Err> {
+        match window.split_once("..") {
+            Some((since, until)) => {
+                let since = parse_time(since)?;
+                let until = match until.trim() {
+                    "" | "now" => None,
+                    until => Some(parse_time(until)?),
+                };
+                if until.is_some_and(|until| until <= since) {
+                    return Err("the end of the range must be after its start".to_string());
+                }
+                Ok(TimeWindow::Range { since, until })
+            }
+            None => parse_time_period(window).map(TimeWindow::Last),
+        }
+    }
+}
+
+/// A job that is still listed but has changed since the last refresh.
+#[derive(Debug, Clone)]
+pub struct JobChange {
+    pub job: SlurmJob,
+    pub old_state: String,
+}
+
+impl JobChange {
+    pub fn state_changed(&self) -> bool {
+        self.job.state != self.old_state
+    }
+
+    /// Whether the job ended since the last refresh.
+    pub fn finished(&self) -> bool {
+        self.job.is_finished() && !is_finished_state(&self.old_state)
+    }
+}
+
+/// How the job list differs from the one sent before it.  Changes include the elapsed time of
+/// running jobs ticking up, `JobChange::state_changed` picks out the jobs that moved on.
+#[derive(Debug, Clone, Default)]
+pub struct JobListDiff {
+    pub added: Vec<SlurmJob>,
+    // ids of the jobs no longer listed, because they left the time window or the query
+    pub removed: Vec<String>,
+    pub changed: Vec<JobChange>,
+}
+
+impl JobListDiff {
+    pub fn new(old: &[SlurmJob], new: &[SlurmJob]) -> Self {
+        let old_jobs: HashMap<&str, &SlurmJob> =
+            old.iter().map(|j| (j.job_id.as_str(), j)).collect();
+        let new_ids: HashSet<&str> = new.iter().map(|j| j.job_id.as_str()).collect();
+        let mut diff = JobListDiff::default();
+        for job in new {
+            match old_jobs.get(job.job_id.as_str()) {
+                None => diff.added.push(job.clone()),
+                Some(old) if *old != job => diff.changed.push(JobChange {
+                    job: job.clone(),
+                    old_state: old.state.clone(),
+                }),
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .iter()
+            .filter(|j| !new_ids.contains(j.job_id.as_str()))
+            .map(|j| j.job_id.clone())
+            .collect();
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Bring a job list sent earlier up to date, keeping it in id order.
+    pub fn apply(self, jobs: &mut Vec<SlurmJob>) {
+        let removed: HashSet<String> = self.removed.into_iter().collect();
+        jobs.retain(|j| !removed.contains(&j.job_id));
+        let mut changed: HashMap<String, SlurmJob> = self
+            .changed
+            .into_iter()
+            .map(|c| (c.job.job_id.clone(), c.job))
+            .collect();
+        for job in jobs.iter_mut() {
+            if let Some(new) = changed.remove(&job.job_id) {
+                *job = new;
+            }
+        }
+        if !self.added.is_empty() {
+            jobs.extend(self.added);
+            jobs.sort_by_key(|j| j.sort_key());
+        }
+    }
+}
 
 struct JobWatcher {
     app: Sender<AppMessage>,
+    receiver: Receiver<JobWatcherMessage>,
     interval: Duration,
     user: String,
-    time_limit: usize,
+    time_window: TimeWindow,
+    query: JobQuery,
+    // the jobs as last sent to the app, what the next refresh is compared against
+    jobs: Vec<SlurmJob>,
 }
 
 #[derive(Debug)]
-pub struct JobWatcherHandle {}
+pub struct JobWatcherHandle {
+    sender: Sender<JobWatcherMessage>,
+    time_window: TimeWindow,
+    query: JobQuery,
+}
 
 impl JobWatcher {
-    fn new(app: Sender<AppMessage>, interval: Duration, user: String, time_limit: usize) -> Self {
+    fn new(
+        app: Sender<AppMessage>,
+        receiver: Receiver<JobWatcherMessage>,
+        interval: Duration,
+        user: String,
+        time_window: TimeWindow,
+        query: JobQuery,
+    ) -> Self {
         Self {
             app,
+            receiver,
             interval,
             user,
-            time_limit,
+            time_window,
+            query,
+            jobs: Vec::new(),
         }
     }
 
-    fn run(&mut self) -> Self {
+    fn run(&mut self) -> Result<(), RecvError> {
+        let ticker = tick(self.interval);
+        self.refresh();
         loop {
-            let job_vec = self.refresh_job_list();
-            self.app.send(AppMessage::JobList(job_vec)).unwrap();
-            thread::sleep(self.interval);
+            select! {
+                recv(self.receiver) -> msg => {
+                    match msg? {
+                        JobWatcherMessage::TimeWindow(time_window) => {
+                            self.time_window = time_window;
+                            self.refresh();
+                        }
+                        JobWatcherMessage::Query(query) => {
+                            self.query = query;
+                            self.refresh();
+                        }
+                    }
+                }
+                recv(ticker) -> _ => self.refresh(),
+            }
+        }
+    }
+
+    fn refresh(&mut self) {
+        // keep the last list if sacct or squeue failed, rather than every job going
+        let Some(job_list) = self.refresh_job_list() else {
+            return;
+        };
+        let diff = JobListDiff::new(&self.jobs, &job_list);
+        self.jobs = job_list;
+        if !diff.is_empty() {
+            self.app.send(AppMessage::JobListDiff(diff)).unwrap();
         }
     }
+
+    /// The jobs matching the query, None if sacct or squeue failed or gave output that doesn't
+    /// parse.  Nothing matching is an empty list.
     pub fn refresh_job_list(&mut self) -> Option<Vec<SlurmJob>> {
-        let cmd = format!(
-        "sacct -u {} -S $(date -d '{} hours ago' +\"%Y-%m-%dT%H:%M:%S\")  \
-        --format=JobID,JobName,Partition,Account,Submit,Start,End,State,WorkDir,Reason,TimeLimit,Elapsed,NodeList  \
-        --parsable2 ", self.user, self.time_limit);
-        let exclude_strings = vec!["batch", "extern", ".0"];
-        let status_map = HashMap::from([
-            ("PENDING", "PD"),
-            ("RUNNING", "R"),
-            ("COMPLETED", "CD"),
-            ("FAILED", "F"),
-            ("CANCELLED", "CA"),
-            ("TIMEOUT", "TO"),
-            ("PREEMPTED", "PR"),
-            ("NODE_FAIL", "NF"),
-            ("REVOKED", "RV"),
-            ("SUSPENDED", "S"),
-        ]);
-        let output = std::process::Command::new("bash")
-            .arg("-c")
-            .arg(cmd)
+        // the query comes from the user, so the commands are run without a shell to interpret it
+        let user = match self.user.is_empty() {
+            true => Vec::new(),
+            false => vec!["-u".to_string(), self.user.clone()],
+        };
+        let status_map = HashMap::from(STATE_CODES);
+        let output = std::process::Command::new("sacct")
+            .args(&user)
+            .args(self.time_window.sacct_args())
+            .arg(
+                "--format=JobID,JobName,Partition,Account,Submit,Start,End,State,WorkDir,Reason,\
+TimeLimit,Elapsed,NodeList,ExitCode,DerivedExitCode,MaxRSS,ReqMem,TotalCPU,AllocCPUS,NNodes,\
+ReqTRES,AllocTRES,ConsumedEnergy,QOS",
+            )
+            .arg("--parsable2")
+            .args(self.query.sacct_args())
             .output()
-            .expect("failed to execute process");
+            .ok()
+            .filter(|output| output.status.success())?;
 
         // work on the string, parse into a SlurmJob struct
         let output = String::from_utf8_lossy(&output.stdout);
-        let mut job_list: Vec<SlurmJob> = Vec::new();
-        output.lines().skip(1).for_each(|line| {
-            let parts = line.split('|').collect::<Vec<&str>>();
-            if let Some(_) = exclude_strings.iter().find(|&&s| parts[0].contains(s)) {
-                return;
-            }
-            if parts[1] == "_interactive" {
-                return;
-            }
-            let job_id = parts[0].to_string();
-            let job_name = parts[1].to_string();
-            let partition = parts[2].to_string();
-            let account = parts[3].to_string();
-            let submit = parts[4].to_string(); // parse this to datetime
-            let start = parts[5].to_string();
-            let end = parts[6].to_string();
-            let state = status_map
-                .get(parts[7].split_whitespace().nth(0).unwrap())
-                .unwrap_or(&parts[7])
-                .to_string();
-            let work_dir = parts[8].to_string();
-            let reason = parts[9].to_string();
-            let time_limit = parts[10].to_string();
-            let elapsed_time = parts[11].to_string();
-            let node_list = parts[12].to_string();
-            // we don't get stdout from sacct, use best guess for completed jobs, otherwise this
-            // will be filled from squeue later.
-            let (stdout, stderr) = (None, None);
-            // read the job script
-            let job_script = std::fs::read_to_string(format!("{}/{}", work_dir, job_name))
-                .ok()
-                .map(|s| s.lines().map(|s| s.to_string()).collect::<Vec<String>>())
-                .unwrap_or_else(|| vec!["No job script found".to_string()]);
-            job_list.push(SlurmJob::new(
-                job_id,
-                job_name,
-                partition,
-                account,
-                state,
-                start,
-                submit,
-                end,
-                reason,
-                work_dir,
-                time_limit,
-                elapsed_time,
-                stdout,
-                stderr,
-                node_list,
-                job_script,
-            ));
-        });
+        if output
+            .lines()
+            .skip(1)
+            .any(|line| line.split('|').count() < SACCT_FIELDS)
+        {
+            return None;
+        }
+        let mut job_list = parse_sacct(&output);
 
         // now run the squeue command, pick up any jobs that don't show in sacct (e.g. jobs pending
         // without a start time etc)
@@ -134,24 +487,35 @@ impl JobWatcher {
             "NodeList",
             "arrayjobid",
             "arraytaskid",
+            "Dependency",
+            "QOS",
         ];
         let squ_formatted_args = squ_args.map(|s| s.to_owned() + ":##").join(",");
-        let cmd = format!(
-            "squeue -u {} --noheader --array --Format {}",
-            self.user, squ_formatted_args
-        );
-        let output = std::process::Command::new("bash")
-            .arg("-c")
-            .arg(cmd)
+        let output = std::process::Command::new("squeue")
+            .args(self.squeue_user_args())
+            .args(["--noheader", "--array"])
+            .args(self.query.squeue_args())
+            .arg("--Format")
+            .arg(squ_formatted_args)
             .output()
-            .expect("Failed to spawn squeue command");
+            .ok()
+            .filter(|output| output.status.success())?;
 
         let output = String::from_utf8_lossy(&output.stdout);
+        if output
+            .lines()
+            .any(|line| line.split("##").count() < squ_args.len())
+        {
+            return None;
+        }
 
         output.lines().for_each(|line| {
             let parts = line.split("##").collect::<Vec<&str>>();
-            let job_id = parts[0].to_string();
             let array_full_jobid = format!("{}_{}", parts[15], parts[16]);
+            let job_id = match parts[16] {
+                "N/A" => parts[0].to_string(),
+                _ => array_full_jobid.clone(),
+            };
 
             if let Some(job) = job_list
                 .iter_mut()
@@ -170,6 +534,7 @@ impl JobWatcher {
                 if job.start == "Unknown" && start_time != "Unknown" {
                     job.start = start_time;
                 }
+                job.dependency = parts[17].to_string();
             } else {
                 let job_name = parts[1].to_string();
                 let partition = parts[2].to_string();
@@ -194,7 +559,7 @@ impl JobWatcher {
                         .ok()
                         .map(|s| s.lines().map(|s| s.to_string()).collect::<Vec<String>>())
                         .unwrap_or_else(|| vec!["No job script found".to_string()]);
-                job_list.push(SlurmJob::new(
+                let mut job = SlurmJob::new(
                     job_id,
                     job_name,
                     partition,
@@ -211,15 +576,153 @@ impl JobWatcher {
                     Some(stderr),
                     node_list,
                     job_script,
-                ));
+                );
+                job.dependency = parts[17].to_string();
+                job.qos = parts[18].to_string();
+                job_list.push(job);
             };
         });
 
-        job_list.sort_by(|a, b| a.job_id.cmp(&b.job_id));
+        // slurm can't match name patterns and the states of array tasks by itself
+        job_list.retain(|job| self.query.matches(job));
+        self.fill_dependencies(&mut job_list);
+        self.fill_pending_estimates(&mut job_list);
+        self.fill_priorities(&mut job_list);
+        job_list.sort_by_key(|j| j.sort_key());
+        Some(job_list)
+    }
+
+    // squeue lists everyone's jobs without a user, unlike sacct
+    fn squeue_user_args(&self) -> Vec<String> {
+        match self.user.is_empty() {
+            true => vec!["--me".to_string()],
+            false => vec!["-u".to_string(), self.user.clone()],
+        }
+    }
+
+    /// Add the scheduler's start time estimate and the queue position to the pending jobs.
+    fn fill_pending_estimates(&self, job_list: &mut [SlurmJob]) {
+        if !job_list.iter().any(|j| j.state == "PD") {
+            return;
+        }
+        // JobArrayID gives tasks as "<array>_<task>", the same as the ids built above
+        let Some(output) = command_output(
+            std::process::Command::new("squeue")
+                .args(self.squeue_user_args())
+                .args(["--start", "--noheader", "--array"])
+                .args(["--Format", "JobArrayID:##,StartTime:##,SchedNodes:##"]),
+        ) else {
+            return;
+        };
+        let estimates: HashMap<&str, (&str, &str)> = output
+            .lines()
+            .filter_map(|line| {
+                let parts = line.split("##").map(|s| s.trim()).collect::<Vec<&str>>();
+                (parts.len() >= 3).then(|| (parts[0], (parts[1], parts[2])))
+            })
+            .collect();
+
+        // every user's pending jobs in the same partitions, the queue the job is waiting in
+        let mut partitions: Vec<&str> = job_list
+            .iter()
+            .filter(|j| j.state == "PD")
+            .flat_map(|j| j.partition.split(','))
+            .collect();
+        partitions.sort();
+        partitions.dedup();
+        // without it the estimates are still filled in, just not the queue positions
+        let output = command_output(
+            std::process::Command::new("squeue")
+                .args(["--noheader", "--array", "-t", "PD", "-p"])
+                .arg(partitions.join(","))
+                .args(["--Format", "JobArrayID:##,Partition:##,PriorityLong:##"]),
+        )
+        .unwrap_or_default();
+        let queue: Vec<(String, String, u64)> = output
+            .lines()
+            .filter_map(|line| {
+                let parts = line.split("##").map(|s| s.trim()).collect::<Vec<&str>>();
+                (parts.len() >= 3).then(|| {
+                    (
+                        parts[0].to_string(),
+                        parts[1].to_string(),
+                        parts[2].parse().unwrap_or(0),
+                    )
+                })
+            })
+            .collect();
+
+        for job in job_list.iter_mut().filter(|j| j.state == "PD") {
+            if let Some((start, nodes)) = estimates.get(job.job_id.as_str()) {
+                job.est_start = start.to_string();
+                job.sched_nodes = match *nodes {
+                    "(null)" => String::new(),
+                    nodes => nodes.to_string(),
+                };
+            }
+            let Some((_, _, priority)) = queue.iter().find(|(id, _, _)| *id == job.job_id) else {
+                continue;
+            };
+            job.queue_position = queue_position(&job.partition, *priority, &queue);
+        }
+    }
+
+    /// Add the sprio breakdown of each pending job's priority.
+    fn fill_priorities(&self, job_list: &mut [SlurmJob]) {
+        if !job_list.iter().any(|j| j.state == "PD") {
+            return;
+        }
+        // without a user sprio lists everyone's jobs, which are looked up by id all the same
+        let user = match self.user.is_empty() {
+            true => Vec::new(),
+            false => vec!["-u".to_string(), self.user.clone()],
+        };
+        let Some(output) = command_output(
+            std::process::Command::new("sprio")
+                .arg("--noheader")
+                .args(user)
+                .args(["-o", "%i|%Y|%A|%F|%J|%P|%Q"]),
+        ) else {
+            return;
+        };
+        let priorities: HashMap<String, Priority> =
+            output.lines().filter_map(parse_sprio).collect();
+        for job in job_list.iter_mut().filter(|j| j.state == "PD") {
+            // sprio lists a pending array that hasn't been split into tasks yet by its array id
+            job.priority = priorities
+                .get(&job.job_id)
+                .or_else(|| job.array_job_id().and_then(|id| priorities.get(id)))
+                .copied();
+        }
+    }
 
-        match job_list.is_empty() {
-            true => None,
-            false => Some(job_list),
+    /// Some squeue versions only report the dependency while it is unfulfilled, ask scontrol
+    /// for jobs still held by one (e.g. DependencyNeverSatisfied) that squeue gave none for.
+    fn fill_dependencies(&self, job_list: &mut [SlurmJob]) {
+        let missing = |j: &SlurmJob| {
+            j.state == "PD"
+                && j.reason.starts_with("Dependency")
+                && parse_dependency(&j.dependency).is_empty()
+        };
+        if !job_list.iter().any(missing) {
+            return;
+        }
+        // a single call for every job rather than one per job, each being a call to slurmctld
+        let Ok(output) = std::process::Command::new("scontrol")
+            .args(["-o", "show", "job"])
+            .output()
+        else {
+            return;
+        };
+        let dependencies = parse_scontrol_dependencies(&String::from_utf8_lossy(&output.stdout));
+        for job in job_list.iter_mut().filter(|j| missing(j)) {
+            // a pending array that hasn't been split into tasks yet is listed once for them all
+            if let Some(dependency) = dependencies
+                .get(&job.job_id)
+                .or_else(|| job.array_job_id().and_then(|id| dependencies.get(id)))
+            {
+                job.dependency = dependency.clone();
+            }
         }
     }
 }
@@ -229,11 +732,177 @@ impl JobWatcherHandle {
         app: Sender<AppMessage>,
         interval: Duration,
         user: String,
-        time_limit: usize,
+        time_window: TimeWindow,
+        query: JobQuery,
     ) -> Self {
-        let mut actor = JobWatcher::new(app, interval, user, time_limit);
+        let (sender, receiver) = unbounded();
+        let mut actor = JobWatcher::new(
+            app,
+            receiver,
+            interval,
+            user,
+            time_window.clone(),
+            query.clone(),
+        );
         thread::spawn(move || actor.run());
 
-        Self {}
+        Self {
+            sender,
+            time_window,
+            query,
+        }
+    }
+
+    pub fn time_window(&self) -> &TimeWindow {
+        &self.time_window
+    }
+
+    /// Change which jobs are listed, the job list is refreshed straight away.
+    pub fn set_time_window(&mut self, time_window: TimeWindow) {
+        if self.time_window != time_window {
+            self.time_window = time_window.clone();
+            self.sender
+                .send(JobWatcherMessage::TimeWindow(time_window))
+                .unwrap();
+        }
+    }
+
+    pub fn query(&self) -> &JobQuery {
+        &self.query
+    }
+
+    /// Ask slurm for only the jobs matching the query, the job list is refreshed straight away.
+    pub fn set_query(&mut self, query: JobQuery) {
+        if self.query != query {
+            self.query = query.clone();
+            self.sender.send(JobWatcherMessage::Query(query)).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_periods() {
+        assert_eq!(parse_time_period("24"), Ok(Duration::from_secs(24 * 3600)));
+        assert_eq!(parse_time_period(" 90m "), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(
+            parse_time_period("1h 30m"),
+            Ok(Duration::from_secs(90 * 60))
+        );
+        assert_eq!(parse_time_period("3d"), Ok(Duration::from_secs(3 * 86400)));
+        assert!(parse_time_period("").is_err());
+        assert!(parse_time_period("-1").is_err());
+        assert!(parse_time_period("soon").is_err());
+    }
+
+    #[test]
+    fn times() {
+        let time = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(parse_time("2024-03-01"), Ok(time("2024-03-01 00:00:00")));
+        assert_eq!(
+            parse_time("2024-03-01T12:30"),
+            Ok(time("2024-03-01 12:30:00"))
+        );
+        assert_eq!(
+            parse_time("2024-03-01 12:30:15"),
+            Ok(time("2024-03-01 12:30:15"))
+        );
+        assert!(parse_time("2024-13-01").is_err());
+        assert!(parse_time("yesterday").is_err());
+    }
+
+    #[test]
+    fn time_windows() {
+        let window = |s: &str| s.parse::<TimeWindow>();
+        assert_eq!(
+            window("3d"),
+            Ok(TimeWindow::Last(Duration::from_secs(3 * 86400)))
+        );
+        assert_eq!(window("3d").unwrap().to_string(), "last 3days");
+        let range = window("2024-03-01..2024-03-05T12:00").unwrap();
+        assert_eq!(range.to_string(), "2024-03-01 00:00 to 2024-03-05 12:00");
+        assert_eq!(
+            range.sacct_args(),
+            ["-S", "2024-03-01T00:00:00", "-E", "2024-03-05T12:00:00"]
+        );
+        let open = window("2024-03-01..").unwrap();
+        assert_eq!(open, window("2024-03-01..now").unwrap());
+        assert_eq!(open.to_string(), "since 2024-03-01 00:00");
+        assert_eq!(open.sacct_args(), ["-S", "2024-03-01T00:00:00"]);
+        assert!(window("2024-03-05..2024-03-01").is_err());
+        assert!(window("2024-03-01..2024-03-01").is_err());
+        assert!(window("..2024-03-01").is_err());
+        // a window too long to subtract from now lists everything
+        let everything = TimeWindow::Last(Duration::MAX).sacct_args();
+        assert_eq!(everything, ["-S", "1970-01-01T00:00:00"]);
+    }
+
+    #[test]
+    fn steps_attach_to_their_job() {
+        let fields = |id: &str, name: &str, state: &str| {
+            format!(
+                "{}|{}|gpu|acct|2024-01-01T00:00:00|2024-01-01T00:01:00|Unknown|{}|/tmp|None|\
+01:00:00|00:10:00|node01|0:0|0:0|1G|4G|00:05:00|4|1|cpu=4|cpu=4|0|normal",
+                id, name, state
+            )
+        };
+        let output = [
+            "JobID|JobName|...".to_string(),
+            fields("12340", "train", "RUNNING"),
+            fields("12340.batch", "batch", "RUNNING"),
+            fields("12340.extern", "extern", "RUNNING"),
+            fields("12340.0", "python", "COMPLETED"),
+            fields("12340.1", "python", "CANCELLED by 1000"),
+            fields("12341", "eval", "PENDING"),
+        ]
+        .join("\n");
+        let jobs = parse_sacct(&output);
+        assert_eq!(
+            jobs.iter().map(|j| j.job_id.as_str()).collect::<Vec<_>>(),
+            ["12340", "12341"]
+        );
+        let steps = jobs[0]
+            .steps
+            .iter()
+            .map(|s| (s.step_id.as_str(), s.state.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [("batch", "R"), ("extern", "R"), ("0", "CD"), ("1", "CA")]
+        );
+        assert_eq!(jobs[0].state, "R");
+        assert_eq!(jobs[1].state, "PD");
+        assert!(jobs[1].steps.is_empty());
+    }
+
+    #[test]
+    fn dependencies_from_scontrol() {
+        let output = "JobId=10 JobName=a Dependency=afterok:9(unfulfilled) Reason=Dependency\n\
+JobId=21 ArrayJobId=20 ArrayTaskId=1 Dependency=afterany:10(unfulfilled)\n\
+JobId=20 ArrayJobId=20 ArrayTaskId=2-9%2 Dependency=afterany:10(unfulfilled)\n\
+JobId=30 JobName=b Reason=None\n";
+        let dependencies = parse_scontrol_dependencies(output);
+        assert_eq!(dependencies.len(), 3);
+        assert_eq!(dependencies["10"], "afterok:9(unfulfilled)");
+        assert_eq!(dependencies["20_1"], "afterany:10(unfulfilled)");
+        assert_eq!(dependencies["20"], "afterany:10(unfulfilled)");
+    }
+
+    #[test]
+    fn queue_position_across_partitions() {
+        let queue = [
+            ("1".to_string(), "a".to_string(), 300),
+            ("2".to_string(), "a,b".to_string(), 200),
+            ("3".to_string(), "b".to_string(), 100),
+            ("4".to_string(), "b".to_string(), 50),
+        ];
+        assert_eq!(queue_position("a,b", 200, &queue), Some((1, 3)));
+        assert_eq!(queue_position("a", 200, &queue), Some((2, 2)));
+        assert_eq!(queue_position("b", 100, &queue), Some((2, 3)));
+        assert_eq!(queue_position("b", 50, &queue), Some((3, 3)));
+        assert_eq!(queue_position("c", 50, &queue), None);
     }
 }
@@ -1,5 +1,21 @@
+pub mod accounts;
+pub mod analysis;
 pub mod app;
-pub mod ui;
-pub mod slurm;
+pub mod cluster;
+pub mod columns;
+pub mod config;
+pub mod debounce;
+pub mod dependency;
 pub mod file_watcher;
+pub mod filter;
+pub mod hooks;
 pub mod job_watcher;
+pub mod node_watcher;
+pub mod notification;
+pub mod pending;
+pub mod query;
+pub mod search;
+pub mod slurm;
+pub mod stat_watcher;
+pub mod ui;
+pub mod watchdog;
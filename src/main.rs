@@ -1,3 +1,4 @@
+use chrono::NaiveDateTime;
 use clap::Parser;
 use crossbeam::channel::{unbounded, Sender};
 use crossterm::event::{self, Event};
@@ -7,19 +8,47 @@ use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
 };
 use swatch::app::{App, AppResult};
+use swatch::config::Config;
+use swatch::job_watcher::{parse_time, parse_time_period, TimeWindow};
+use swatch::query::{parse_job_id_range, JobQuery};
+use swatch::slurm::state_code;
 // use futility::event::{Even t, EventHandler};
-use fern;
 use ratatui::backend::{Backend, CrosstermBackend};
 use ratatui::Terminal;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 use std::{io, thread};
 
 #[derive(Parser)]
 struct CLIArgs {
-    #[clap(short, long, default_value = "24")]
-    time_period: usize,
+    /// How far back to list jobs, e.g. "90m" or "3d", a bare number is hours
+    #[clap(short, long, default_value = "24", value_parser = parse_time_period)]
+    time_period: Duration,
+    /// List jobs from this time on instead, e.g. "2024-03-01" or "2024-03-01T12:00"
+    #[clap(long, value_parser = parse_time, conflicts_with = "time_period")]
+    since: Option<NaiveDateTime>,
+    /// With --since, list jobs up to this time rather than now
+    #[clap(long, value_parser = parse_time, requires = "since")]
+    until: Option<NaiveDateTime>,
     #[clap(short, long, default_value = "")]
     user: String,
+    /// Only list jobs in these states, e.g. "R,PD" or "F,TO,OOM"
+    #[clap(long, value_delimiter = ',')]
+    state: Vec<String>,
+    /// Only list jobs in these partitions
+    #[clap(long, value_delimiter = ',')]
+    partition: Vec<String>,
+    /// Only list jobs charged to these accounts
+    #[clap(long, value_delimiter = ',')]
+    account: Vec<String>,
+    /// Only list jobs with these names, '*' and '?' match any characters
+    #[clap(long, value_delimiter = ',')]
+    name: Vec<String>,
+    /// Only list these jobs, e.g. "1200-1300,1450"
+    #[clap(long, value_delimiter = ',', value_parser = parse_job_id_range)]
+    id: Vec<(u64, u64)>,
+    /// Only list jobs with these QOS
+    #[clap(long, value_delimiter = ',')]
+    qos: Vec<String>,
     #[clap(short, long)]
     running: bool,
 }
@@ -44,6 +73,7 @@ fn setup_logger() -> Result<(), fern::InitError> {
 
 fn main() -> AppResult<()> {
     let args = CLIArgs::parse();
+    let config = Config::load()?;
     // Create an application.
     setup_logger()?;
 
@@ -52,7 +82,7 @@ fn main() -> AppResult<()> {
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(io::stderr());
     let mut terminal = Terminal::new(backend)?;
-    run_app(&mut terminal, args)?;
+    run_app(&mut terminal, args, config)?;
 
     disable_raw_mode()?;
     execute!(
@@ -65,17 +95,40 @@ fn main() -> AppResult<()> {
     Ok(())
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, args: CLIArgs) -> io::Result<()> {
+fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    args: CLIArgs,
+    config: Config,
+) -> io::Result<()> {
     let (input_tx, input_rx) = unbounded();
     let slurm_refresh_rate = 30;
     let file_refresh_rate = 10;
+    let stat_refresh_rate = 10;
+    let time_window = match args.since {
+        Some(since) => TimeWindow::Range {
+            since,
+            until: args.until,
+        },
+        None => TimeWindow::Last(args.time_period),
+    };
+    let query = JobQuery {
+        states: args.state.iter().map(|s| state_code(s)).collect(),
+        partitions: args.partition,
+        accounts: args.account,
+        names: args.name,
+        job_ids: args.id,
+        qos: args.qos,
+    };
     let mut app = App::new(
         input_rx,
         args.user,
-        args.time_period,
+        time_window,
+        query,
         slurm_refresh_rate,
         file_refresh_rate,
+        stat_refresh_rate,
         args.running,
+        config,
     );
 
     thread::spawn(move || input_loop(input_tx));
@@ -88,3 +141,17 @@ fn input_loop(tx: Sender<io::Result<Event>>) {
         tx.send(event::read()).unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn since_conflicts_with_time_period() {
+        let args = CLIArgs::try_parse_from(["swatch", "--since", "2024-03-01"]).unwrap();
+        assert!(args.since.is_some());
+        assert_eq!(args.time_period, Duration::from_secs(24 * 3600));
+        assert!(CLIArgs::try_parse_from(["swatch", "-t", "3d", "--since", "2024-03-01"]).is_err());
+        assert!(CLIArgs::try_parse_from(["swatch", "--until", "2024-03-01"]).is_err());
+    }
+}
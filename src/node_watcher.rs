@@ -0,0 +1,227 @@
+use std::{collections::HashMap, thread, time::Duration};
+
+use crossbeam::{
+    channel::{tick, unbounded, Receiver, RecvError, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+use crate::debounce::{Debounce, MIN_REFRESH_GAP};
+use crate::slurm::parse_mem;
+
+enum NodeWatcherMessage {
+    Nodes(Vec<String>),
+}
+
+/// Health of a single node, from `scontrol show node`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeDetail {
+    pub name: String,
+    // e.g. "MIXED", "IDLE+DRAIN", "DOWN*"
+    pub state: String,
+    pub reason: String,
+    pub cpu_load: Option<f64>,
+    pub cpus_alloc: u64,
+    pub cpus_total: u64,
+    // memory values are in bytes
+    pub real_memory: Option<f64>,
+    pub alloc_memory: Option<f64>,
+    pub free_memory: Option<f64>,
+    pub gres: String,
+    pub gres_used: String,
+}
+
+impl NodeDetail {
+    /// Whether the node is in a state that would hurt a job running on it.
+    pub fn is_unhealthy(&self) -> bool {
+        ["DOWN", "DRAIN", "FAIL", "NOT_RESPONDING", "INVAL"]
+            .iter()
+            .any(|s| self.state.contains(s))
+            || self.state.ends_with('*')
+    }
+}
+
+/// Parse the one line per node output of `scontrol -o show node`.
+pub fn parse_scontrol_nodes(output: &str) -> Vec<NodeDetail> {
+    output
+        .lines()
+        .filter(|line| line.starts_with("NodeName="))
+        .map(|line| {
+            let fields = parse_fields(line);
+            let get = |key: &str| fields.get(key).cloned().unwrap_or_default();
+            let number = |key: &str| get(key).parse::<f64>().ok();
+            NodeDetail {
+                name: get("NodeName"),
+                state: get("State"),
+                reason: get("Reason"),
+                cpu_load: number("CPULoad"),
+                cpus_alloc: get("CPUAlloc").parse().unwrap_or(0),
+                cpus_total: get("CPUTot").parse().unwrap_or(0),
+                real_memory: parse_mem(&get("RealMemory")),
+                alloc_memory: parse_mem(&get("AllocMem")),
+                free_memory: parse_mem(&get("FreeMem")),
+                gres: get("Gres").replace("(null)", ""),
+                gres_used: get("GresUsed").replace("(null)", ""),
+            }
+        })
+        .collect()
+}
+
+// split "Key=value Key2=value with spaces" into a map, values like Reason can contain spaces so
+// words without an '=' belong to the previous value
+fn parse_fields(line: &str) -> HashMap<String, String> {
+    let mut fields: HashMap<String, String> = HashMap::new();
+    let mut last_key: Option<String> = None;
+    for word in line.split_whitespace() {
+        match word.split_once('=') {
+            Some((key, value)) if key.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                fields.insert(key.to_string(), value.to_string());
+                last_key = Some(key.to_string());
+            }
+            _ => {
+                if let Some(value) = last_key.as_ref().and_then(|k| fields.get_mut(k)) {
+                    value.push(' ');
+                    value.push_str(word);
+                }
+            }
+        }
+    }
+    fields
+}
+
+struct NodeWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<NodeWatcherMessage>,
+    interval: Duration,
+    nodes: Vec<String>,
+    debounce: Debounce,
+}
+
+impl NodeWatcher {
+    fn new(
+        app: Sender<AppMessage>,
+        receiver: Receiver<NodeWatcherMessage>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            app,
+            receiver,
+            interval,
+            nodes: Vec::new(),
+            debounce: Debounce::new(MIN_REFRESH_GAP),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), RecvError> {
+        let ticker = tick(self.interval);
+        loop {
+            select! {
+                recv(self.receiver) -> msg => {
+                    match msg? {
+                        NodeWatcherMessage::Nodes(nodes) => {
+                            self.nodes = nodes;
+                            if self.debounce.request() {
+                                self.refresh();
+                            }
+                        }
+                    }
+                }
+                recv(self.debounce.timer()) -> _ => self.refresh(),
+                recv(ticker) -> _ => self.refresh(),
+            }
+        }
+    }
+
+    fn refresh(&mut self) {
+        // an empty refresh serves a pending request too, or its timer would keep firing
+        self.debounce.refreshed();
+        if self.nodes.is_empty() {
+            return;
+        }
+        // keep the last details if scontrol fails, the next tick tries again
+        let Some(output) = std::process::Command::new("scontrol")
+            .args(["-o", "show", "node"])
+            .arg(self.nodes.join(","))
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+        else {
+            return;
+        };
+        let output = String::from_utf8_lossy(&output.stdout);
+        self.app
+            .send(AppMessage::NodeDetails(parse_scontrol_nodes(&output)))
+            .unwrap();
+    }
+}
+
+#[derive(Debug)]
+pub struct NodeWatcherHandle {
+    sender: Sender<NodeWatcherMessage>,
+    nodes: Vec<String>,
+}
+
+impl NodeWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, interval: Duration) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = NodeWatcher::new(app, receiver, interval);
+        thread::spawn(move || actor.run());
+        Self {
+            sender,
+            nodes: Vec::new(),
+        }
+    }
+
+    /// Set the nodes to poll scontrol for, an empty list stops polling.
+    pub fn set_nodes(&mut self, nodes: Vec<String>) {
+        if self.nodes != nodes {
+            self.nodes = nodes.clone();
+            self.sender.send(NodeWatcherMessage::Nodes(nodes)).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::slurm::{compress_hostlist, expand_hostlist};
+
+    #[test]
+    fn fields_keep_spaces_in_values() {
+        let fields = parse_fields(
+            "NodeName=gpu01 State=DOWN* Reason=Not responding [slurm@2024] Gres=gpu:2",
+        );
+        assert_eq!(fields["NodeName"], "gpu01");
+        assert_eq!(fields["State"], "DOWN*");
+        assert_eq!(fields["Reason"], "Not responding [slurm@2024]");
+        assert_eq!(fields["Gres"], "gpu:2");
+        assert!(parse_fields("").is_empty());
+    }
+
+    #[test]
+    fn scontrol_nodes() {
+        let output = "NodeName=gpu01 Arch=x86_64 CPUAlloc=16 CPUTot=64 CPULoad=15.80 \
+Gres=gpu:a100:4(S:0-1) GresUsed=gpu:a100:1(IDX:0) RealMemory=512000 AllocMem=128000 \
+FreeMem=300000 State=MIXED Reason=(null)\n\
+NodeName=gpu02 CPUAlloc=0 CPUTot=64 CPULoad=N/A Gres=(null) RealMemory=512000 \
+State=IDLE+DRAIN Reason=bad gpu [root@2024-01-01T00:00:00]\n\
+No nodes in the system\n";
+        let nodes = parse_scontrol_nodes(output);
+        assert_eq!(nodes.len(), 2);
+        assert_eq!(nodes[0].name, "gpu01");
+        assert_eq!((nodes[0].cpus_alloc, nodes[0].cpus_total), (16, 64));
+        assert_eq!(nodes[0].cpu_load, Some(15.8));
+        assert_eq!(nodes[0].real_memory, Some(512000. * 1024. * 1024.));
+        assert_eq!(nodes[0].gres_used, "gpu:a100:1(IDX:0)");
+        assert!(!nodes[0].is_unhealthy());
+        assert_eq!(nodes[1].cpu_load, None);
+        assert_eq!(nodes[1].gres, "");
+        assert_eq!(nodes[1].free_memory, None);
+        assert_eq!(nodes[1].reason, "bad gpu [root@2024-01-01T00:00:00]");
+        assert!(nodes[1].is_unhealthy());
+        // the names read back are the ones the job's node list expands to
+        let names = nodes.iter().map(|n| n.name.clone()).collect::<Vec<_>>();
+        assert_eq!(expand_hostlist("gpu[01-02]"), names);
+        assert_eq!(compress_hostlist(&names), "gpu[01-02]");
+    }
+}
@@ -0,0 +1,225 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+/// A change of job state worth a notification, e.g. "R->F", where "*" stands for any state.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Transition {
+    // None for any state
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+impl Transition {
+    pub fn matches(&self, from: &str, to: &str) -> bool {
+        self.from.as_ref().is_none_or(|s| s == from) && self.to.as_ref().is_none_or(|s| s == to)
+    }
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = |s: &Option<String>| s.clone().unwrap_or_else(|| "*".to_string());
+        write!(f, "{}->{}", state(&self.from), state(&self.to))
+    }
+}
+
+impl FromStr for Transition {
+    type Err = String;
+
+    fn from_str(transition: &str) -> Result<Self, Self::Err> {
+        let (from, to) = transition.split_once("->").ok_or_else(|| {
+            format!(
+                "notify transition \"{}\" has no \"->\", expected one like \"R->F\" or \"*->TO\"",
+                transition
+            )
+        })?;
+        let state = |s: &str| match s.trim() {
+            "*" | "any" | "" => None,
+            s => Some(s.to_uppercase()),
+        };
+        Ok(Transition {
+            from: state(from),
+            to: state(to),
+        })
+    }
+}
+
+impl TryFrom<String> for Transition {
+    type Error = String;
+
+    fn try_from(transition: String) -> Result<Self, Self::Error> {
+        transition.parse()
+    }
+}
+
+impl From<Transition> for String {
+    fn from(transition: Transition) -> Self {
+        transition.to_string()
+    }
+}
+
+/// How a notification is delivered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Method {
+    // the terminal bell, which tmux turns into a bell flag on the window
+    Bell,
+    // iTerm2, kitty, WezTerm, Windows Terminal and others
+    Osc9,
+    // rxvt-unicode and VTE based terminals
+    Osc777,
+    // notify-send, when there is a D-Bus session to send it over
+    Desktop,
+}
+
+/// The `[notify]` section of the config.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotifyConfig {
+    pub transitions: Vec<Transition>,
+    pub methods: Vec<Method>,
+    // only the jobs watched with 'w' notify, rather than every job
+    pub watched_only: bool,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            transitions: ["R->F", "PD->R", "*->TO"]
+                .iter()
+                .filter_map(|t| t.parse().ok())
+                .collect(),
+            methods: vec![Method::Bell, Method::Osc9, Method::Desktop],
+            watched_only: true,
+        }
+    }
+}
+
+impl NotifyConfig {
+    pub fn wants(&self, from: &str, to: &str) -> bool {
+        self.transitions.iter().any(|t| t.matches(from, to))
+    }
+}
+
+/// Sends notifications by whichever of the configured methods work where swatch is running.
+#[derive(Debug)]
+pub struct Notifier {
+    desktop: bool,
+    // escape sequences have to be wrapped to get through tmux to the terminal
+    tmux: bool,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        let dbus = std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_some_and(|a| !a.is_empty());
+        let desktop = dbus
+            && std::process::Command::new("bash")
+                .arg("-c")
+                .arg("command -v notify-send")
+                .output()
+                .is_ok_and(|output| output.status.success());
+        Self {
+            desktop,
+            tmux: std::env::var_os("TMUX").is_some(),
+        }
+    }
+
+    pub fn send(&self, methods: &[Method], title: &str, body: &str) {
+        // job names are user input, keep them from ending the escape sequences early
+        let clean = |s: &str| s.chars().filter(|c| !c.is_control()).collect::<String>();
+        let (title, body) = (clean(title), clean(body));
+        let mut escapes = String::new();
+        for method in methods {
+            match method {
+                Method::Bell => escapes.push('\x07'),
+                Method::Osc9 => escapes.push_str(&self.osc(&format!("9;{}: {}", title, body))),
+                Method::Osc777 => escapes.push_str(&self.osc(&format!(
+                    "777;notify;{};{}",
+                    title.replace(';', ","),
+                    body.replace(';', ",")
+                ))),
+                Method::Desktop if self.desktop => {
+                    let (title, body) = (title.clone(), body.clone());
+                    // don't hold up the ui if the notification daemon is slow
+                    thread::spawn(move || {
+                        let _ = std::process::Command::new("bash")
+                            .arg("-c")
+                            .arg(r#"notify-send "$1" "$2""#)
+                            .arg("swatch")
+                            .arg(title)
+                            .arg(body)
+                            .output();
+                    });
+                }
+                Method::Desktop => {}
+            }
+        }
+        if !escapes.is_empty() {
+            let mut stdout = std::io::stdout();
+            let _ = stdout.write_all(escapes.as_bytes());
+            let _ = stdout.flush();
+        }
+    }
+
+    fn osc(&self, command: &str) -> String {
+        match self.tmux {
+            true => format!("\x1bPtmux;\x1b\x1b]{}\x07\x1b\\", command),
+            false => format!("\x1b]{}\x07", command),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        let t: Transition = "r->f".parse().unwrap();
+        assert_eq!(t.from.as_deref(), Some("R"));
+        assert_eq!(t.to.as_deref(), Some("F"));
+        assert!(t.matches("R", "F"));
+        assert!(!t.matches("PD", "F"));
+        let t: Transition = "any -> TO".parse().unwrap();
+        assert_eq!(t.from, None);
+        assert!(t.matches("PD", "TO") && t.matches("R", "TO"));
+        assert!(!t.matches("R", "F"));
+        let t: Transition = "*->".parse().unwrap();
+        assert_eq!((t.from.clone(), t.to.clone()), (None, None));
+        assert_eq!(t.to_string(), "*->*");
+        assert!("RF"
+            .parse::<Transition>()
+            .unwrap_err()
+            .contains("\"RF\" has no \"->\""));
+    }
+
+    #[test]
+    fn bad_transitions_are_named() {
+        let e = toml::from_str::<NotifyConfig>("transitions = [\"R->F\", \"RF\"]\n").unwrap_err();
+        assert!(e.to_string().contains("\"RF\" has no \"->\""), "{}", e);
+    }
+
+    #[test]
+    fn wants() {
+        let config = NotifyConfig::default();
+        assert!(config.wants("R", "F"));
+        assert!(config.wants("PD", "R"));
+        assert!(config.wants("R", "TO") && config.wants("PD", "TO"));
+        assert!(!config.wants("R", "CD"));
+        let config = NotifyConfig {
+            transitions: Vec::new(),
+            ..NotifyConfig::default()
+        };
+        assert!(!config.wants("R", "F"));
+    }
+}
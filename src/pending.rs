@@ -0,0 +1,377 @@
+/// Weighted priority factors of a pending job, as printed by sprio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Priority {
+    pub total: u64,
+    pub age: u64,
+    pub fairshare: u64,
+    pub job_size: u64,
+    pub partition: u64,
+    pub qos: u64,
+}
+
+impl Priority {
+    /// The factors with their share of the total, largest first, e.g. "fairshare 8000 (80%)".
+    pub fn breakdown(&self) -> Vec<(&'static str, u64, f64)> {
+        let mut factors = vec![
+            ("age", self.age),
+            ("fairshare", self.fairshare),
+            ("job size", self.job_size),
+            ("partition", self.partition),
+            ("qos", self.qos),
+        ];
+        factors.sort_by_key(|(_, value)| std::cmp::Reverse(*value));
+        factors
+            .into_iter()
+            .map(|(name, value)| {
+                let share = match self.total {
+                    0 => 0.,
+                    total => value as f64 / total as f64,
+                };
+                (name, value, share)
+            })
+            .collect()
+    }
+}
+
+/// Parse a line of `sprio --noheader -o "%i|%Y|%A|%F|%J|%P|%Q"` into the job id and factors.
+pub fn parse_sprio(line: &str) -> Option<(String, Priority)> {
+    let parts = line.split('|').map(|s| s.trim()).collect::<Vec<&str>>();
+    if parts.len() < 7 {
+        return None;
+    }
+    // sprio prints decimals with --norm, and some versions pad integers
+    let value = |i: usize| parts[i].parse::<f64>().unwrap_or(0.).round() as u64;
+    Some((
+        parts[0].to_string(),
+        Priority {
+            total: value(1),
+            age: value(2),
+            fairshare: value(3),
+            job_size: value(4),
+            partition: value(5),
+            qos: value(6),
+        },
+    ))
+}
+
+/// What a pending reason means and what could be done about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReasonExplanation {
+    pub explanation: String,
+    pub suggestion: String,
+}
+
+/// Explain a squeue pending reason, e.g. "QOSMaxJobsPerUserLimit" or
+/// "ReqNodeNotAvail, UnavailableNodes:node[01-02]".
+pub fn explain_reason(reason: &str) -> Option<ReasonExplanation> {
+    // some reasons carry details after the code
+    let code = reason
+        .trim_matches(['(', ')'])
+        .split([',', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim();
+    let (explanation, suggestion) = match code {
+        "Priority" => (
+            "Higher priority jobs are queued ahead of this one in the partition",
+            "Wait, or submit to a less busy partition; a shorter time limit can let backfill start it sooner",
+        ),
+        "Resources" => (
+            "The job is next in line and waiting for enough nodes, CPUs, memory or GPUs to free up",
+            "Wait; requesting fewer resources or a shorter time limit may start it sooner",
+        ),
+        "Dependency" => (
+            "The job is waiting for the jobs it depends on to reach the required state",
+            "Check the dependency panel for the jobs it is waiting on",
+        ),
+        "DependencyNeverSatisfied" => (
+            "A job this one depends on ended in a way that can never satisfy the dependency",
+            "Cancel it (X cancels a job with its dependents) or scontrol update its dependency",
+        ),
+        "BeginTime" => (
+            "The job was submitted with --begin and its start time hasn't been reached",
+            "Wait, or scontrol update jobid=<id> StartTime=now",
+        ),
+        "JobHeldUser" => (
+            "The job was held by its owner",
+            "Release it with scontrol release <id>",
+        ),
+        "JobHeldAdmin" => (
+            "The job was held by an administrator",
+            "Ask the cluster admins why it was held",
+        ),
+        "ReqNodeNotAvail" => (
+            "A node the job needs is down, drained or reserved, often for upcoming maintenance",
+            "Check for a maintenance reservation, shorten the time limit to finish before it, or drop --nodelist",
+        ),
+        "PartitionDown" | "PartitionInactive" => (
+            "The partition isn't accepting jobs to run right now",
+            "Wait for it to come back up or resubmit to another partition",
+        ),
+        "PartitionNodeLimit" => (
+            "The job asks for more nodes than the partition allows per job",
+            "Reduce --nodes or use a partition that allows more",
+        ),
+        "PartitionTimeLimit" => (
+            "The job's time limit is longer than the partition allows",
+            "Lower --time below the partition limit, shown in the cluster view",
+        ),
+        "PartitionConfig" => (
+            "The job requests something the partition isn't configured to provide",
+            "Check the partition's limits and allowed accounts and QOS",
+        ),
+        "NodeDown" => (
+            "A node the job needs is down",
+            "Wait for the node to return or resubmit excluding it",
+        ),
+        "BadConstraints" => (
+            "No node can satisfy the job's constraints",
+            "Check --constraint, --gres and memory requests against the cluster view",
+        ),
+        "InvalidAccount" => (
+            "The job's account is invalid or the user isn't a member of it",
+            "Cancel and resubmit with a valid --account",
+        ),
+        "InvalidQOS" => (
+            "The job's QOS is invalid or not allowed for its account",
+            "Cancel and resubmit with a valid --qos",
+        ),
+        "Licenses" => (
+            "The job is waiting for a license to become available",
+            "Wait for other jobs to release it",
+        ),
+        "Reservation" => (
+            "The job is waiting for its advanced reservation to become available",
+            "Wait for the reservation to start",
+        ),
+        "ReservationDeleted" => (
+            "The reservation the job was submitted to has been deleted",
+            "Cancel and resubmit without --reservation",
+        ),
+        "BurstBufferResources" | "BurstBufferStageIn" => (
+            "The job is waiting for burst buffer space or data stage in",
+            "Wait",
+        ),
+        "Cleaning" => (
+            "The job is being requeued and is still cleaning up after its previous run",
+            "Wait",
+        ),
+        "Prolog" => ("The job's prolog script is still running", "Wait"),
+        "WaitingForScheduling" | "None" => (
+            "The scheduler hasn't looked at the job yet",
+            "Wait for the next scheduling cycle",
+        ),
+        "launch failed requeued held" | "JobLaunchFailure" => (
+            "The job failed to launch and was held, usually because of a node or filesystem problem",
+            "Check the job's nodes and release it with scontrol release <id>",
+        ),
+        "SystemFailure" => (
+            "Slurm, the filesystem or the network failed",
+            "Ask the cluster admins",
+        ),
+        "AssociationJobLimit" | "QOSJobLimit" => (
+            "The job's account or QOS is at its maximum number of jobs",
+            "Wait for other jobs under the same limit to finish",
+        ),
+        "AssociationResourceLimit" | "QOSResourceLimit" => (
+            "The job's account or QOS is at a resource limit",
+            "Wait for other jobs under the same limit to finish",
+        ),
+        "AssociationTimeLimit" | "QOSTimeLimit" => (
+            "The job's account or QOS is at a time limit",
+            "Lower --time or wait",
+        ),
+        "QOSUsageThreshold" => (
+            "The QOS has used up its allowed usage",
+            "Wait for usage to decay or use another QOS",
+        ),
+        _ => return explain_limit(code),
+    };
+    Some(ReasonExplanation {
+        explanation: explanation.to_string(),
+        suggestion: suggestion.to_string(),
+    })
+}
+
+// the account and QOS limits are named by a pattern, e.g. AssocGrpCPULimit,
+// QOSMaxGRESPerUser or AssocGrpMemRunMinutes
+fn explain_limit(code: &str) -> Option<ReasonExplanation> {
+    let (owner, rest) = if let Some(rest) = code.strip_prefix("Assoc") {
+        ("account", rest)
+    } else if let Some(rest) = code.strip_prefix("QOS") {
+        ("QOS", rest)
+    } else {
+        return None;
+    };
+    let resource = |limit: &str| -> String {
+        let (name, unit) = [
+            ("CPU", "CPUs", "CPU"),
+            ("Mem", "memory", "memory"),
+            ("Node", "nodes", "node"),
+            ("GRES", "GPUs or other GRES", "GRES"),
+            ("Billing", "billing units", "billing"),
+            ("Energy", "energy", "energy"),
+            ("Jobs", "jobs", "job"),
+            ("Submit", "submitted jobs", "job"),
+            ("Wall", "wall time", "wall time"),
+        ]
+        .iter()
+        // slurm isn't consistent about the case, e.g. QOSMaxCpuPerJobLimit but AssocGrpCPULimit
+        .find(|(prefix, _, _)| {
+            limit
+                .to_ascii_lowercase()
+                .starts_with(&prefix.to_ascii_lowercase())
+        })
+        .map_or(("resources", "resource"), |(_, name, unit)| (name, unit));
+        // time based limits, e.g. GrpCPUMins or GrpCPURunMins(Limit|utes)
+        if limit.contains("RunMin") {
+            format!("{} minutes of running jobs", unit)
+        } else if limit.contains("Mins") {
+            format!("{} minutes", unit)
+        } else {
+            name.to_string()
+        }
+    };
+    let per = |limit: &str| {
+        ["PerUser", "PerJob", "PerAccount", "PerNode"]
+            .iter()
+            .find(|per| limit.contains(*per))
+            .map_or("per user", |per| match *per {
+                "PerJob" => "per job",
+                "PerAccount" => "per account",
+                "PerNode" => "per node",
+                _ => "per user",
+            })
+    };
+    let (explanation, suggestion) = if let Some(limit) = rest.strip_prefix("Grp") {
+        let resource = resource(limit);
+        let suggestion = match limit.contains("RunMin") {
+            // run minutes count the time left of running jobs, so a shorter job fits sooner
+            true => "Wait for running jobs to finish, or lower --time so the job fits".to_string(),
+            false => format!(
+                "Wait for other jobs in the same {} to finish, or request fewer {}",
+                owner, resource
+            ),
+        };
+        (
+            format!("The {} as a whole is at its limit on {}", owner, resource),
+            suggestion,
+        )
+    } else if let Some(limit) = rest.strip_prefix("Max") {
+        let resource = resource(limit);
+        let suggestion = match resource.as_str() {
+            "wall time" => "Lower --time below the limit".to_string(),
+            "jobs" | "submitted jobs" => "Wait for some of your other jobs to finish".to_string(),
+            _ => format!("Request fewer {} or wait for your other jobs", resource),
+        };
+        (
+            format!(
+                "The job would go over the {}'s {} limit on {}",
+                owner,
+                per(limit),
+                resource
+            ),
+            suggestion,
+        )
+    } else if let Some(limit) = rest.strip_prefix("Min") {
+        let resource = resource(limit);
+        (
+            format!(
+                "The job asks for less than the {}'s minimum {}",
+                owner, resource
+            ),
+            format!("Request more {} or use another {}", resource, owner),
+        )
+    } else {
+        return None;
+    };
+    Some(ReasonExplanation {
+        explanation,
+        suggestion,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sprio_lines() {
+        let (job_id, priority) = parse_sprio("  12345_7|  10500| 1000.4|8000|500|1000|0").unwrap();
+        assert_eq!(job_id, "12345_7");
+        assert_eq!(
+            priority,
+            Priority {
+                total: 10500,
+                age: 1000,
+                fairshare: 8000,
+                job_size: 500,
+                partition: 1000,
+                qos: 0,
+            }
+        );
+        assert_eq!(parse_sprio("12345|100|x|||0|0").unwrap().1.age, 0);
+        assert_eq!(parse_sprio("12345|100|0"), None);
+        assert_eq!(parse_sprio(""), None);
+    }
+
+    #[test]
+    fn breakdown_is_largest_first() {
+        let (_, priority) = parse_sprio("1|10000|1000|8000|0|1000|0").unwrap();
+        let breakdown = priority.breakdown();
+        assert_eq!(breakdown[0], ("fairshare", 8000, 0.8));
+        assert_eq!(breakdown[1].1, 1000);
+        assert_eq!(breakdown[4].1, 0);
+        // a zero total doesn't divide by zero
+        assert!(Priority::default().breakdown().iter().all(|f| f.2 == 0.));
+    }
+
+    #[test]
+    fn details_after_the_code_are_ignored() {
+        let plain = explain_reason("ReqNodeNotAvail").unwrap();
+        assert_eq!(
+            explain_reason("ReqNodeNotAvail, UnavailableNodes:node[01-02]"),
+            Some(plain.clone())
+        );
+        assert_eq!(explain_reason("(ReqNodeNotAvail)"), Some(plain));
+        assert_eq!(explain_reason("(Priority)"), explain_reason("Priority"));
+        assert!(explain_reason("SomethingNew").is_none());
+        assert!(explain_reason("").is_none());
+    }
+
+    #[test]
+    fn limits_fall_through_to_the_pattern() {
+        // named outright rather than by the pattern
+        let named = explain_reason("QOSJobLimit").unwrap();
+        assert!(named.explanation.contains("maximum number of jobs"));
+
+        let grp = explain_reason("AssocGrpGRES").unwrap();
+        assert_eq!(
+            grp.explanation,
+            "The account as a whole is at its limit on GPUs or other GRES"
+        );
+        let run_mins = explain_reason("AssocGrpCPURunMinutesLimit").unwrap();
+        assert_eq!(
+            run_mins.explanation,
+            "The account as a whole is at its limit on CPU minutes of running jobs"
+        );
+        assert!(run_mins.suggestion.contains("--time"));
+        let max = explain_reason("QOSMaxCpuPerJobLimit").unwrap();
+        assert_eq!(
+            max.explanation,
+            "The job would go over the QOS's per job limit on CPUs"
+        );
+        let wall = explain_reason("QOSMaxWallDurationPerJobLimit").unwrap();
+        assert_eq!(wall.suggestion, "Lower --time below the limit");
+        let min = explain_reason("QOSMinGRES").unwrap();
+        assert_eq!(
+            min.suggestion,
+            "Request more GPUs or other GRES or use another QOS"
+        );
+        assert!(explain_reason("QOSSomethingElse").is_none());
+        assert!(explain_reason("AssocMaxJobsLimit")
+            .unwrap()
+            .suggestion
+            .starts_with("Wait for some"));
+    }
+}
@@ -0,0 +1,280 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::slurm::{state_code, SlurmJob};
+
+// expanding more job ids than this into a -j list makes for a command line longer than it is
+// worth, larger ranges are only filtered once the jobs are back
+const MAX_LISTED_IDS: u64 = 1000;
+
+/// Which jobs to ask sacct and squeue for, so that slurm does the filtering rather than swatch
+/// having to parse every job in a long history.  An empty field matches anything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JobQuery {
+    // short state codes, e.g. "R", "PD", "TO", see `state_code`
+    pub states: Vec<String>,
+    pub partitions: Vec<String>,
+    pub accounts: Vec<String>,
+    // exact names or glob patterns, e.g. "sweep_*"
+    pub names: Vec<String>,
+    // inclusive ranges of (array) job ids
+    pub job_ids: Vec<(u64, u64)>,
+    pub qos: Vec<String>,
+}
+
+impl JobQuery {
+    pub fn is_empty(&self) -> bool {
+        *self == JobQuery::default()
+    }
+
+    /// The filtering options for sacct, e.g. ["-s", "R,PD", "-r", "gpu"].
+    pub fn sacct_args(&self) -> Vec<String> {
+        self.args(["-s", "-r", "-A", "-q", "--name", "-j"])
+    }
+
+    /// The filtering options for squeue, e.g. ["-t", "R,PD", "-p", "gpu"].
+    pub fn squeue_args(&self) -> Vec<String> {
+        self.args(["-t", "-p", "-A", "-q", "-n", "-j"])
+    }
+
+    // each option followed by its comma separated values, as separate arguments rather than a
+    // command line, so that no value is ever interpreted by a shell
+    fn args(&self, options: [&str; 6]) -> Vec<String> {
+        let join = |values: &[String]| (!values.is_empty()).then(|| values.join(","));
+        let values = [
+            join(&self.states),
+            join(&self.partitions),
+            join(&self.accounts),
+            join(&self.qos),
+            self.exact_names(),
+            self.listed_ids(),
+        ];
+        options
+            .iter()
+            .zip(values)
+            .filter_map(|(option, values)| Some([option.to_string(), values?]))
+            .flatten()
+            .collect()
+    }
+
+    // slurm only matches names exactly, patterns are left to `matches`
+    fn exact_names(&self) -> Option<String> {
+        match self.names.is_empty() || self.names.iter().any(|n| is_pattern(n)) {
+            true => None,
+            false => Some(self.names.join(",")),
+        }
+    }
+
+    fn listed_ids(&self) -> Option<String> {
+        let count = self
+            .job_ids
+            .iter()
+            .map(|(a, b)| b.saturating_sub(*a).saturating_add(1))
+            .fold(0u64, u64::saturating_add);
+        if self.job_ids.is_empty() || count > MAX_LISTED_IDS {
+            return None;
+        }
+        let ids = self
+            .job_ids
+            .iter()
+            .flat_map(|(a, b)| *a..=*b)
+            .map(|id| id.to_string())
+            .collect::<Vec<String>>();
+        Some(ids.join(","))
+    }
+
+    /// Whether a job fits the query.  Slurm has already done most of the filtering, this catches
+    /// what it can't do: name patterns, large id ranges and the steps of a state sacct matched.
+    pub fn matches(&self, job: &SlurmJob) -> bool {
+        let any = |values: &[String], value: &str| {
+            values.is_empty() || values.iter().any(|v| v.eq_ignore_ascii_case(value))
+        };
+        let id = job.sort_key().0;
+        any(&self.states, &job.state)
+            && any(&self.partitions, &job.partition)
+            && any(&self.accounts, &job.account)
+            && any(&self.qos, &job.qos)
+            && (self.names.is_empty() || self.names.iter().any(|n| glob_match(n, &job.job_name)))
+            && (self.job_ids.is_empty()
+                || self.job_ids.iter().any(|(a, b)| (*a..=*b).contains(&id)))
+    }
+}
+
+fn is_pattern(name: &str) -> bool {
+    name.contains(['*', '?'])
+}
+
+/// Match shell style wildcards, '*' for any run of characters and '?' for any one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<char>>();
+    let text = text.chars().collect::<Vec<char>>();
+    let (mut p, mut t) = (0, 0);
+    // where to go back to when what followed the last '*' stops matching
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, start)) => {
+                    p = star + 1;
+                    t = start + 1;
+                    backtrack = Some((star, start + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Parse job id ranges, e.g. "1200-1300,1450".
+pub fn parse_job_ids(ids: &str) -> Result<Vec<(u64, u64)>, String> {
+    ids.split(',')
+        .filter(|range| !range.trim().is_empty())
+        .map(parse_job_id_range)
+        .collect()
+}
+
+/// Parse a single job id, e.g. "1450", or an inclusive range of them, e.g. "1200-1300".
+pub fn parse_job_id_range(range: &str) -> Result<(u64, u64), String> {
+    let parse = |id: &str| {
+        id.trim()
+            .parse::<u64>()
+            .map_err(|_| format!("{}: expected a job id or a range like 100-200", range))
+    };
+    match range.split_once('-') {
+        Some((start, end)) => {
+            let (start, end) = (parse(start)?, parse(end)?);
+            match start <= end {
+                true => Ok((start, end)),
+                false => Err(format!("{}: the range is backwards", range)),
+            }
+        }
+        None => parse(range).map(|id| (id, id)),
+    }
+}
+
+impl fmt::Display for JobQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids = self
+            .job_ids
+            .iter()
+            .map(|(a, b)| match a == b {
+                true => a.to_string(),
+                false => format!("{}-{}", a, b),
+            })
+            .collect::<Vec<String>>();
+        let fields = [
+            ("state", &self.states),
+            ("partition", &self.partitions),
+            ("account", &self.accounts),
+            ("name", &self.names),
+            ("id", &ids),
+            ("qos", &self.qos),
+        ];
+        let terms = fields
+            .iter()
+            .filter(|(_, values)| !values.is_empty())
+            .map(|(key, values)| format!("{}:{}", key, values.join(",")))
+            .collect::<Vec<String>>();
+        write!(f, "{}", terms.join(" "))
+    }
+}
+
+impl FromStr for JobQuery {
+    type Err = String;
+
+    /// Space separated terms, e.g. "state:R,PD partition:gpu name:sweep_* id:1200-1300".
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        let mut parsed = JobQuery::default();
+        for term in query.split_whitespace() {
+            let (key, values) = term
+                .split_once(':')
+                .ok_or_else(|| format!("{}: expected key:value", term))?;
+            let list = || {
+                values
+                    .split(',')
+                    .filter(|v| !v.is_empty())
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>()
+            };
+            match key {
+                "state" => parsed.states.extend(list().iter().map(|s| state_code(s))),
+                "partition" => parsed.partitions.extend(list()),
+                "account" => parsed.accounts.extend(list()),
+                "name" => parsed.names.extend(list()),
+                "id" => parsed.job_ids.extend(parse_job_ids(values)?),
+                "qos" => parsed.qos.extend(list()),
+                _ => {
+                    return Err(format!(
+                        "{}: unknown key, expected state, partition, account, name, id or qos",
+                        key
+                    ))
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_are_kept_as_short_codes() {
+        let query: JobQuery = "state:running,pd,Timeout,OOM".parse().unwrap();
+        assert_eq!(query.states, ["R", "PD", "TO", "OOM"]);
+        let job = SlurmJob {
+            state: "R".to_string(),
+            ..Default::default()
+        };
+        assert!(query.matches(&job));
+    }
+
+    #[test]
+    fn values_are_separate_arguments() {
+        let query: JobQuery = "partition:x;rm name:a$(b) id:5-6".parse().unwrap();
+        assert_eq!(
+            query.squeue_args(),
+            ["-p", "x;rm", "-n", "a$(b)", "-j", "5,6"]
+        );
+        assert!(JobQuery::default().sacct_args().is_empty());
+    }
+
+    #[test]
+    fn huge_id_ranges_are_not_listed() {
+        let query: JobQuery = "id:0-18446744073709551615,3".parse().unwrap();
+        assert!(query.sacct_args().is_empty());
+        assert!("id:5-3".parse::<JobQuery>().is_err());
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("sweep_*", "sweep_lr"));
+        assert!(glob_match("sweep_*", "sweep_"));
+        assert!(glob_match("s?eep", "sweep"));
+        assert!(!glob_match("s?eep", "seep"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+        assert!(!glob_match("?", ""));
+    }
+
+    #[test]
+    fn glob_match_backtracks_over_stars() {
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("*ab", "aab"));
+        assert!(glob_match("*a*a*", "banana"));
+        assert!(glob_match("**x", "yyx"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+        assert!(!glob_match("*ab", "aba"));
+    }
+}
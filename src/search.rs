@@ -0,0 +1,172 @@
+use crate::app::JobRow;
+
+// points for each matched character, and the bonuses and penalties on top of it
+const MATCH_SCORE: i64 = 16;
+const CONSECUTIVE_BONUS: i64 = 12;
+const BOUNDARY_BONUS: i64 = 10;
+// a long gap costs no more than this, "a_b" and "a_long_word_b" are both scattered matches
+const MAX_GAP_PENALTY: i64 = 8;
+const MAX_LEADING_PENALTY: i64 = 12;
+
+/// The job fields the finder looks in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchField {
+    Id,
+    Name,
+    Partition,
+    WorkDir,
+}
+
+/// A job matching the search, with where in which field the typed characters were found.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchHit {
+    // the job id, "123_4" for an array task
+    pub id: String,
+    pub name: String,
+    pub field: SearchField,
+    pub text: String,
+    // character indices of the matched characters in `text`
+    pub positions: Vec<usize>,
+    pub score: i64,
+}
+
+/// Fuzzy match the pattern against the text, case insensitively, requiring every character of
+/// the pattern in order.  Returns a score, higher is better, and the matched character
+/// positions.  Runs of characters and matches at the start of words score higher.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let pattern = pattern
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<Vec<char>>();
+    let first = *pattern.first()?;
+    let chars = text.chars().collect::<Vec<char>>();
+    let lower = chars
+        .iter()
+        .map(|c| c.to_ascii_lowercase())
+        .collect::<Vec<char>>();
+
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    // the first character fixes where the rest can go, so try each place it appears
+    for start in (0..lower.len()).filter(|&i| lower[i] == first) {
+        let mut positions = vec![start];
+        for c in &pattern[1..] {
+            let from = positions[positions.len() - 1] + 1;
+            match lower[from..].iter().position(|l| l == c) {
+                Some(offset) => positions.push(from + offset),
+                None => break,
+            }
+        }
+        // starting any later won't find what starting here couldn't
+        if positions.len() < pattern.len() {
+            break;
+        }
+        let score = score(&chars, &positions);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+fn score(text: &[char], positions: &[usize]) -> i64 {
+    let mut score = -(positions[0] as i64).min(MAX_LEADING_PENALTY);
+    for (n, &p) in positions.iter().enumerate() {
+        score += MATCH_SCORE;
+        let boundary = p == 0
+            || !text[p - 1].is_alphanumeric()
+            || (text[p - 1].is_lowercase() && text[p].is_uppercase());
+        if boundary {
+            score += BOUNDARY_BONUS;
+        }
+        if n > 0 {
+            match p - positions[n - 1] - 1 {
+                0 => score += CONSECUTIVE_BONUS,
+                gap => score -= (gap as i64).min(MAX_GAP_PENALTY),
+            }
+        }
+    }
+    score
+}
+
+/// Rank the jobs in the table against the pattern, best first.  The tasks of collapsed arrays
+/// are searched too, so a task can be found without expanding its array first.
+pub fn search_jobs(rows: &[JobRow], pattern: &str) -> Vec<SearchHit> {
+    let mut hits = Vec::new();
+    for row in rows {
+        let jobs = match row {
+            // an expanded array lists its tasks again, they are searched with the array
+            JobRow::Job(job) if job.array_job_id().is_some() => continue,
+            JobRow::Job(job) => vec![job],
+            JobRow::Array(array) => array.tasks.iter().collect(),
+            // steps share their parent's fields, the parent row is enough
+            JobRow::Step(..) => continue,
+        };
+        for job in jobs {
+            let fields = [
+                (SearchField::Id, &job.job_id),
+                (SearchField::Name, &job.job_name),
+                (SearchField::Partition, &job.partition),
+                (SearchField::WorkDir, &job.work_dir),
+            ];
+            let best = fields
+                .iter()
+                .filter_map(|(field, text)| {
+                    fuzzy_match(pattern, text)
+                        .map(|(score, positions)| (field, text, score, positions))
+                })
+                .max_by_key(|(_, _, score, _)| *score);
+            if let Some((field, text, score, positions)) = best {
+                hits.push(SearchHit {
+                    id: job.job_id.clone(),
+                    name: job.job_name.clone(),
+                    field: *field,
+                    text: text.to_string(),
+                    positions,
+                    score,
+                });
+            }
+        }
+    }
+    // a stable sort keeps the table's order between equally good matches
+    hits.sort_by_key(|hit| std::cmp::Reverse(hit.score));
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_match_needs_every_character_in_order() {
+        assert_eq!(
+            fuzzy_match("swp", "sweep").map(|(_, p)| p),
+            Some(vec![0, 1, 4])
+        );
+        assert_eq!(
+            fuzzy_match("SW P", "sweep").map(|(_, p)| p),
+            Some(vec![0, 1, 4])
+        );
+        assert!(fuzzy_match("pws", "sweep").is_none());
+        assert!(fuzzy_match("sweeps", "sweep").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_empty_input() {
+        assert!(fuzzy_match("", "sweep").is_none());
+        assert!(fuzzy_match("  ", "sweep").is_none());
+        assert!(fuzzy_match("a", "").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_runs_and_word_starts() {
+        let score = |pattern: &str, text: &str| fuzzy_match(pattern, text).unwrap().0;
+        assert!(score("lr", "train_lr") > score("lr", "train_large_run"));
+        assert!(score("tr", "x_train") > score("tr", "xtrain"));
+        // the best of several places to start is the one kept
+        assert_eq!(
+            fuzzy_match("run", "rxuxn_run").map(|(_, p)| p),
+            Some(vec![6, 7, 8])
+        );
+    }
+}
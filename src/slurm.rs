@@ -1,13 +1,14 @@
-use std::{str::FromStr, thread, time::Duration};
+use std::{path::PathBuf, str::FromStr, thread};
 
-use chrono::{NaiveTime, Timelike};
+use chrono::{Local, NaiveDateTime, NaiveTime, TimeZone, Timelike};
 use crossbeam::{
     channel::{Receiver, Sender},
     select,
 };
 
-use crate::app::{AppMessage, JobControlMessage};
-use anyhow::Result;
+use crate::app::{AppMessage, JobControlMessage, Resubmission};
+use crate::pending::Priority;
+use anyhow::{anyhow, Result};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
 pub struct SlurmJob {
@@ -27,9 +28,44 @@ pub struct SlurmJob {
     pub stderr: Option<String>,
     pub node_list: String,
     pub job_script: Vec<String>,
+    // "status:signal" pairs as reported by sacct, e.g. "0:9"
+    pub exit_code: String,
+    pub derived_exit_code: String,
+    pub req_mem: String,
+    pub steps: Vec<JobStep>,
+    pub total_cpu: String,
+    pub alloc_cpus: String,
+    pub alloc_nodes: String,
+    pub req_tres: String,
+    pub alloc_tres: String,
+    pub consumed_energy: String,
+    pub qos: String,
+    // raw --dependency string from squeue, e.g. "afterok:123(unfulfilled)"
+    pub dependency: String,
+    // backfill scheduler's estimate from squeue --start, only set for pending jobs
+    pub est_start: String,
+    pub sched_nodes: String,
+    // (position, pending jobs) in the job's partition, ordered by priority
+    pub queue_position: Option<(usize, usize)>,
+    pub priority: Option<Priority>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct JobStep {
+    // the part of the sacct JobID after the '.', e.g. "batch", "extern", "0"
+    pub step_id: String,
+    pub step_name: String,
+    pub state: String,
+    pub exit_code: String,
+    pub elapsed_time: String,
+    pub node_list: String,
+    pub max_rss: String,
+    pub total_cpu: String,
+    pub alloc_cpus: String,
 }
 
 impl SlurmJob {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         job_id: String,
         job_name: String,
@@ -65,6 +101,7 @@ impl SlurmJob {
             stderr,
             node_list,
             job_script,
+            ..Default::default()
         }
     }
 }
@@ -117,6 +154,627 @@ impl SlurmJob {
             elapsed_time
         }
     }
+
+    /// The id of the array this job is a task of, "123" for "123_4".
+    pub fn array_job_id(&self) -> Option<&str> {
+        self.job_id
+            .split_once('_')
+            .map(|(array_job_id, _)| array_job_id)
+    }
+
+    pub fn array_task_id(&self) -> Option<u64> {
+        self.job_id.split_once('_')?.1.parse().ok()
+    }
+
+    /// Numeric ordering of job ids, so that "123_10" sorts after "123_2".
+    pub fn sort_key(&self) -> (u64, Option<u64>) {
+        let base = self.array_job_id().unwrap_or(&self.job_id);
+        (base.parse().unwrap_or(u64::MAX), self.array_task_id())
+    }
+
+    pub fn is_finished(&self) -> bool {
+        is_finished_state(&self.state)
+    }
+
+    /// Where the job writes its output, slurm's default "slurm-<id>.out" in the work dir if
+    /// squeue didn't say.
+    pub fn stdout_path(&self) -> PathBuf {
+        match &self.stdout {
+            Some(stdout) => PathBuf::from(stdout),
+            None => PathBuf::from(format!("{}/slurm-{}.out", self.work_dir, self.job_id)),
+        }
+    }
+
+    /// Where the job writes its errors, the same file as its output unless it was split off.
+    pub fn stderr_path(&self) -> PathBuf {
+        match &self.stderr {
+            Some(stderr) => PathBuf::from(stderr),
+            None => self.stdout_path(),
+        }
+    }
+
+    /// Requested memory for the whole job in bytes, taking into account the per-cpu ("c") and
+    /// per-node ("n") suffixes older slurm versions append to ReqMem.
+    pub fn req_mem_bytes(&self) -> Option<f64> {
+        let req_mem = parse_mem(&self.req_mem)?;
+        let multiplier = if self.req_mem.ends_with('c') {
+            self.alloc_cpus.parse::<f64>().unwrap_or(1.)
+        } else if self.req_mem.ends_with('n') {
+            self.alloc_nodes.parse::<f64>().unwrap_or(1.)
+        } else {
+            1.
+        };
+        Some(req_mem * multiplier)
+    }
+
+    /// Fraction of the allocated core-walltime that was actually spent on the cpu.
+    pub fn cpu_efficiency(&self) -> Option<f64> {
+        let total_cpu = parse_duration(&self.total_cpu)?;
+        let elapsed = parse_duration(&self.elapsed_time)?;
+        let alloc_cpus = self.alloc_cpus.parse::<f64>().ok()?;
+        if elapsed == 0. || alloc_cpus == 0. {
+            return None;
+        }
+        Some(total_cpu / (elapsed * alloc_cpus))
+    }
+
+    /// Fraction of the requested memory that was used at peak.
+    pub fn mem_efficiency(&self) -> Option<f64> {
+        let req_mem = self.req_mem_bytes()?;
+        if req_mem == 0. {
+            return None;
+        }
+        Some(self.max_rss_bytes()? / req_mem)
+    }
+
+    /// Largest MaxRSS over all steps of the job, in bytes.
+    pub fn max_rss_bytes(&self) -> Option<f64> {
+        self.steps
+            .iter()
+            .filter_map(|s| parse_mem(&s.max_rss))
+            .reduce(f64::max)
+    }
+
+    pub fn memory_summary(&self) -> Option<String> {
+        let max_rss = self.max_rss_bytes()?;
+        match self.req_mem_bytes() {
+            Some(req_mem) => Some(format!(
+                "MaxRSS {} of {}",
+                format_mem(max_rss),
+                format_mem(req_mem)
+            )),
+            None => Some(format!("MaxRSS {}", format_mem(max_rss))),
+        }
+    }
+
+    /// Compact "step:state" list, e.g. "batch:OOM extern:CD 0:F".
+    pub fn step_summary(&self) -> String {
+        self.steps
+            .iter()
+            .map(|s| format!("{}:{}", s.step_id, s.state))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+
+    /// Interpret the exit codes and step states of a finished job into a one line explanation
+    /// of why it ended.
+    pub fn diagnose(&self) -> Option<String> {
+        if !self.is_finished() {
+            return None;
+        }
+        let failed_step = self
+            .steps
+            .iter()
+            .find(|s| !["CD", "CA"].contains(&s.state.as_str()));
+        let in_step = failed_step
+            .map(|s| format!(" in step {}", s.step_id))
+            .unwrap_or_default();
+        let near_mem_limit = self.mem_efficiency().is_some_and(|e| e > 0.9);
+
+        if self.state == "OOM" || self.steps.iter().any(|s| s.state == "OOM") {
+            return Some(match self.memory_summary() {
+                Some(mem) => format!("ran out of memory{} ({})", in_step, mem),
+                None => format!("ran out of memory{}", in_step),
+            });
+        }
+        match self.state.as_str() {
+            "TO" => {
+                return Some(format!(
+                    "hit the time limit ({} of {})",
+                    self.elapsed_time, self.time_limit
+                ))
+            }
+            "NF" => return Some(format!("node failure on {}", self.node_list)),
+            "BF" => return Some(format!("node boot failure on {}", self.node_list)),
+            "DL" => return Some("reached its deadline".to_string()),
+            "PR" => return Some("preempted by a higher priority job".to_string()),
+            _ => {}
+        }
+
+        // a signal on the job or on any of the steps takes priority over the exit status
+        let (status, signal) = parse_exit_code(&self.exit_code).unwrap_or((0, 0));
+        let (derived_status, derived_signal) =
+            parse_exit_code(&self.derived_exit_code).unwrap_or((0, 0));
+        let step_signal = self
+            .steps
+            .iter()
+            .filter_map(|s| parse_exit_code(&s.exit_code))
+            .map(|(_, signal)| signal)
+            .find(|&signal| signal != 0)
+            .unwrap_or(0);
+        let signal = [signal, derived_signal, step_signal]
+            .into_iter()
+            .find(|&s| s != 0);
+        if let Some(signal) = signal {
+            let detail = match (signal, near_mem_limit, self.memory_summary()) {
+                (9, true, Some(mem)) => format!("likely OOM, {}", mem),
+                _ => signal_name(signal).to_string(),
+            };
+            return Some(format!(
+                "killed by signal {}{} ({})",
+                signal, in_step, detail
+            ));
+        }
+        let status = if status != 0 { status } else { derived_status };
+        if status != 0 {
+            let hint = match status {
+                1 => "general error".to_string(),
+                2 => "misuse of shell builtin or bad arguments".to_string(),
+                126 => "command not executable".to_string(),
+                127 => "command not found".to_string(),
+                s if s > 128 => format!("shell reported {}", signal_name(s - 128)),
+                _ => "non-zero exit".to_string(),
+            };
+            return Some(format!("exited with code {}{} ({})", status, in_step, hint));
+        }
+        match self.state.as_str() {
+            "CD" => Some("completed successfully".to_string()),
+            "CA" => Some("cancelled".to_string()),
+            "F" => Some(format!("failed{}", in_step)),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a short state code is one a job ends in, anything but running, pending or suspended.
+pub fn is_finished_state(state: &str) -> bool {
+    !["R", "PD", "S"].contains(&state)
+}
+
+/// Expand slurm array index syntax, e.g. "1-5,8,10-20:5%4", into the individual indices.
+pub fn expand_ranges(ranges: &str) -> Vec<u64> {
+    // anything after a '%' is the max number of simultaneously running tasks
+    let ranges = ranges.split('%').next().unwrap_or_default();
+    let mut indices = Vec::new();
+    for range in ranges.split(',') {
+        let (range, step) = match range.split_once(':') {
+            Some((range, step)) => (range, step.parse().unwrap_or(1).max(1)),
+            None => (range, 1),
+        };
+        match range.split_once('-') {
+            Some((start, end)) => {
+                if let (Ok(start), Ok(end)) = (start.parse::<u64>(), end.parse::<u64>()) {
+                    indices.extend((start..=end).step_by(step));
+                }
+            }
+            None => indices.extend(range.parse::<u64>().ok()),
+        }
+    }
+    indices
+}
+
+/// Slurm's long state names and the short codes squeue shows and swatch keeps jobs in.
+pub const STATE_CODES: [(&str, &str); 13] = [
+    ("PENDING", "PD"),
+    ("RUNNING", "R"),
+    ("COMPLETED", "CD"),
+    ("FAILED", "F"),
+    ("CANCELLED", "CA"),
+    ("TIMEOUT", "TO"),
+    ("PREEMPTED", "PR"),
+    ("NODE_FAIL", "NF"),
+    ("REVOKED", "RV"),
+    ("SUSPENDED", "S"),
+    ("OUT_OF_MEMORY", "OOM"),
+    ("BOOT_FAIL", "BF"),
+    ("DEADLINE", "DL"),
+];
+
+/// The short code for a state given either way, e.g. "running" or "R" become "R".
+pub fn state_code(state: &str) -> String {
+    let state = state.trim().to_uppercase();
+    STATE_CODES
+        .iter()
+        .find(|(name, _)| *name == state)
+        .map(|(_, code)| code.to_string())
+        .unwrap_or(state)
+}
+
+/// Expand slurm hostlist syntax, e.g. "node[012-015,020],gpu01", into the individual host
+/// names, keeping the zero padding of the ranges.
+pub fn expand_hostlist(hostlist: &str) -> Vec<String> {
+    let mut hosts = Vec::new();
+    // commas separate hosts, except inside brackets where they separate ranges
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in hostlist.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                hosts.extend(expand_host(&hostlist[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    hosts.extend(expand_host(&hostlist[start..]));
+    hosts
+}
+
+// expand a single host pattern, which may have several bracketed ranges, e.g. "r[1-2]n[01-02]"
+fn expand_host(host: &str) -> Vec<String> {
+    let host = host.trim();
+    if host.is_empty() || host == "None assigned" || host == "(null)" {
+        return Vec::new();
+    }
+    let Some((prefix, rest)) = host.split_once('[') else {
+        return vec![host.to_string()];
+    };
+    let Some((ranges, suffix)) = rest.split_once(']') else {
+        return vec![host.to_string()];
+    };
+    let mut names = Vec::new();
+    for range in ranges.split(',') {
+        let (first, last) = range.split_once('-').unwrap_or((range, range));
+        let (Ok(start), Ok(end)) = (first.parse::<u64>(), last.parse::<u64>()) else {
+            continue;
+        };
+        for i in start..=end {
+            names.push(format!("{}{:0width$}", prefix, i, width = first.len()));
+        }
+    }
+    let suffixes = expand_host(suffix);
+    if suffixes.is_empty() {
+        return names;
+    }
+    names
+        .iter()
+        .flat_map(|name| suffixes.iter().map(move |s| format!("{}{}", name, s)))
+        .collect()
+}
+
+/// Compress host names into slurm hostlist syntax, the inverse of [`expand_hostlist`], e.g.
+/// ["node012", "node013", "node020", "gpu01"] becomes "gpu01,node[012-013,020]".
+pub fn compress_hostlist(hosts: &[String]) -> String {
+    // group hosts by their prefix and the width of their trailing number
+    let mut groups: Vec<(String, usize, Vec<u64>)> = Vec::new();
+    let mut others: Vec<String> = Vec::new();
+    for host in hosts {
+        let digits = host.len() - host.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let (prefix, number) = host.split_at(host.len() - digits);
+        let Ok(number) = number.parse::<u64>() else {
+            others.push(host.clone());
+            continue;
+        };
+        match groups
+            .iter_mut()
+            .find(|(p, width, _)| p == prefix && *width == digits)
+        {
+            Some((_, _, numbers)) => numbers.push(number),
+            None => groups.push((prefix.to_string(), digits, vec![number])),
+        }
+    }
+    groups.sort();
+    others.sort();
+    others.dedup();
+    let mut hostlist = others;
+    for (prefix, width, mut numbers) in groups {
+        numbers.sort_unstable();
+        numbers.dedup();
+        if let [number] = numbers[..] {
+            hostlist.push(format!("{}{:0width$}", prefix, number));
+            continue;
+        }
+        let mut ranges: Vec<(u64, u64)> = Vec::new();
+        for number in numbers {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == number => *end = number,
+                _ => ranges.push((number, number)),
+            }
+        }
+        let ranges = ranges
+            .iter()
+            .map(|(start, end)| match start == end {
+                true => format!("{:0width$}", start),
+                false => format!("{:0width$}-{:0width$}", start, end),
+            })
+            .collect::<Vec<String>>()
+            .join(",");
+        hostlist.push(format!("{}[{}]", prefix, ranges));
+    }
+    hostlist.join(",")
+}
+
+/// Compress array indices into slurm's range syntax, the inverse of [`expand_ranges`], e.g.
+/// [3, 17, 42, 43, 44, 45] becomes "3,17,42-45".
+pub fn compress_ranges(indices: &[u64]) -> String {
+    let mut indices = indices.to_vec();
+    indices.sort_unstable();
+    indices.dedup();
+    let mut ranges: Vec<(u64, u64)> = Vec::new();
+    for index in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == index => *end = index,
+            _ => ranges.push((index, index)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end),
+        })
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// The command line a job was submitted with, from sacct's SubmitLine or, on clusters too old to
+/// record it, the script scontrol still knows about.
+fn submit_command(job_id: &str) -> Option<String> {
+    let output = std::process::Command::new("sacct")
+        .args(["-X", "-n", "-P", "--format=SubmitLine", "-j", job_id])
+        .output()
+        .ok()?;
+    let submit_line = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|l| l.trim().to_string())
+        .find(|l| !l.is_empty());
+    if submit_line.is_some() {
+        return submit_line;
+    }
+    // only the script and its arguments, any options given to sbatch by hand are lost
+    let output = std::process::Command::new("scontrol")
+        .args(["show", "job", job_id])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("Command="))
+        .filter(|command| !command.is_empty() && *command != "(null)")
+        .map(|command| format!("sbatch {}", command))
+}
+
+// sbatch options that don't take a value, or only take one after an '='
+const SBATCH_FLAGS: [&str; 32] = [
+    "-H",
+    "-h",
+    "-I",
+    "-k",
+    "-O",
+    "-Q",
+    "-s",
+    "-V",
+    "-v",
+    "-W",
+    "--contiguous",
+    "--exclusive",
+    "--get-user-env",
+    "--help",
+    "--hold",
+    "--ignore-pbs",
+    "--no-kill",
+    "--no-requeue",
+    "--nice",
+    "--overcommit",
+    "--oversubscribe",
+    "--parsable",
+    "--quiet",
+    "--reboot",
+    "--requeue",
+    "--spread-job",
+    "--test-only",
+    "--usage",
+    "--use-min-nodes",
+    "--verbose",
+    "--version",
+    "--wait",
+];
+
+/// Split a command line into words the way the shell it was typed into would have, with quotes
+/// and backslashes taken out.  None if a quote isn't closed.
+fn shell_words(command: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            _ if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        // inside double quotes a backslash only escapes these
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            '\n' => {}
+                            c => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => {
+                let word = word.get_or_insert_with(String::new);
+                match chars.next() {
+                    Some('\n') | None => {}
+                    Some(c) => word.push(c),
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Some(words)
+}
+
+/// The sbatch arguments that resubmit a submit command for just the given array indices, with
+/// the original --array and --parsable dropped.  None if it wasn't an sbatch command, or its
+/// quoting doesn't parse.
+pub fn resubmit_args(command: &str, indices: &str) -> Option<Vec<String>> {
+    let takes_value = |word: &str| {
+        !word.contains('=')
+            && !SBATCH_FLAGS.contains(&word)
+            && (word.starts_with("--") || word.len() == 2)
+    };
+    let mut words = shell_words(command)?.into_iter();
+    if !words.next()?.ends_with("sbatch") {
+        return None;
+    }
+    let mut args = vec!["--parsable".to_string(), format!("--array={}", indices)];
+    while let Some(word) = words.next() {
+        match word.as_str() {
+            // sbatch takes the script and its arguments as they are
+            _ if !word.starts_with('-') => {
+                args.push(word);
+                args.extend(words);
+                break;
+            }
+            "--array" | "-a" => {
+                words.next();
+            }
+            "--parsable" => {}
+            _ if word.starts_with("--array=") => {}
+            _ if word.starts_with("-a") && !word.starts_with("--") => {}
+            // "-J name" and "--job-name name" carry their value in the next word
+            _ if takes_value(&word) => {
+                args.push(word);
+                args.extend(words.next());
+            }
+            _ => args.push(word),
+        }
+    }
+    Some(args)
+}
+
+/// Parse a sacct "status:signal" exit code, e.g. "0:9".
+pub fn parse_exit_code(exit_code: &str) -> Option<(u32, u32)> {
+    let (status, signal) = exit_code.split_once(':')?;
+    Some((status.parse().ok()?, signal.parse().ok()?))
+}
+
+pub fn signal_name(signal: u32) -> &'static str {
+    match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        4 => "SIGILL",
+        6 => "SIGABRT",
+        7 => "SIGBUS",
+        8 => "SIGFPE",
+        9 => "SIGKILL",
+        10 => "SIGUSR1",
+        11 => "SIGSEGV",
+        12 => "SIGUSR2",
+        13 => "SIGPIPE",
+        14 => "SIGALRM",
+        15 => "SIGTERM",
+        24 => "SIGXCPU",
+        25 => "SIGXFSZ",
+        _ => "unknown signal",
+    }
+}
+
+/// Parse a slurm memory value ("64G", "4000Mn", "123456K") into bytes.  Bare numbers are
+/// treated as megabytes, which is slurm's default unit.
+pub fn parse_mem(mem: &str) -> Option<f64> {
+    let mem = mem.trim().trim_end_matches(['n', 'c']);
+    if mem.is_empty() {
+        return None;
+    }
+    let (value, unit) = match mem.char_indices().last()? {
+        (i, c) if c.is_ascii_alphabetic() => (&mem[..i], c.to_ascii_uppercase()),
+        _ => (mem, 'M'),
+    };
+    let multiplier = match unit {
+        'K' => 1024_f64,
+        'M' => 1024_f64.powi(2),
+        'G' => 1024_f64.powi(3),
+        'T' => 1024_f64.powi(4),
+        'P' => 1024_f64.powi(5),
+        _ => return None,
+    };
+    value.parse::<f64>().ok().map(|v| v * multiplier)
+}
+
+/// Parse a slurm duration ("[D-]HH:MM:SS", "MM:SS.mmm", "D-HH", ...) into seconds.
+pub fn parse_duration(duration: &str) -> Option<f64> {
+    let (days, rest) = match duration.trim().split_once('-') {
+        Some((days, rest)) => (Some(days.parse::<f64>().ok()?), rest),
+        None => (None, duration.trim()),
+    };
+    let parts = rest
+        .split(':')
+        .map(|p| p.parse::<f64>().ok())
+        .collect::<Option<Vec<f64>>>()?;
+    // with a day prefix the first field is always hours, otherwise a lone field is minutes
+    let (hours, minutes, seconds) = match (days.is_some(), parts.as_slice()) {
+        (_, [h, m, s]) => (*h, *m, *s),
+        (true, [h, m]) => (*h, *m, 0.),
+        (true, [h]) => (*h, 0., 0.),
+        (false, [m, s]) => (0., *m, *s),
+        (false, [m]) => (0., *m, 0.),
+        _ => return None,
+    };
+    Some(days.unwrap_or(0.) * 86400. + hours * 3600. + minutes * 60. + seconds)
+}
+
+/// Format a number of seconds as a slurm duration, "[D-]HH:MM:SS".
+pub fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.max(0.) as u64;
+    let (days, hours, minutes, seconds) = (
+        seconds / 86400,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60,
+    );
+    match days {
+        0 => format!("{:02}:{:02}:{:02}", hours, minutes, seconds),
+        _ => format!("{}-{:02}:{:02}:{:02}", days, hours, minutes, seconds),
+    }
+}
+
+/// Seconds from now until a slurm timestamp, e.g. "2024-03-01T12:00:00", negative if it has
+/// passed.  None for anything that isn't a time, like "N/A" or "Unknown".
+pub fn seconds_until(timestamp: &str) -> Option<i64> {
+    let time = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S").ok()?;
+    let time = Local.from_local_datetime(&time).earliest()?;
+    Some((time - Local::now()).num_seconds())
+}
+
+/// Format a number of bytes the way slurm does, e.g. "63.9G".
+pub fn format_mem(bytes: f64) -> String {
+    let units = ["K", "M", "G", "T", "P"];
+    let mut value = bytes / 1024.;
+    let mut unit = 0;
+    while value >= 1024. && unit < units.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+    let value = format!("{:.1}", value);
+    format!("{}{}", value.trim_end_matches(".0"), units[unit])
 }
 
 #[derive(Debug)]
@@ -132,8 +790,8 @@ impl SlurmJobControl {
         Self { send, recv }
     }
 
-    fn cancel_job(&self, job_id: &str) -> Result<()> {
-        let cmd = format!("scancel {}", job_id);
+    fn cancel_job(&self, job_ids: &[String]) -> Result<()> {
+        let cmd = format!("scancel {}", job_ids.join(" "));
         std::process::Command::new("bash")
             .arg("-c")
             .arg(cmd)
@@ -142,6 +800,29 @@ impl SlurmJobControl {
         Ok(())
     }
 
+    /// Send a signal to every step of a job, including the batch script, or cancel it if there
+    /// is no signal.
+    fn signal_job(&self, job_id: &str, signal: Option<&str>) -> Result<()> {
+        // the signal comes from the config, pass it as an argument rather than as shell code
+        let mut command = std::process::Command::new("bash");
+        match signal {
+            Some(signal) => command
+                .arg("-c")
+                .arg(r#"scancel --full --signal="$1" "$2""#)
+                .arg("swatch")
+                .arg(signal),
+            None => command.arg("-c").arg(r#"scancel "$1""#).arg("swatch"),
+        };
+        let output = command.arg(job_id).output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "scancel failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(())
+    }
+
     fn requeue_job(&self, job: &SlurmJob) -> Result<()> {
         let cmd = format!("sbatch {}", job.job_name);
         // execute in the work_dir
@@ -154,6 +835,38 @@ impl SlurmJobControl {
         Ok(())
     }
 
+    /// Resubmit an array job for just the given indices, e.g. "3,17,42-45", with the command it
+    /// was submitted with, returning the id of the new array.
+    fn resubmit_tasks(&self, job: &SlurmJob, indices: &str) -> Result<Resubmission> {
+        let array_job_id = job.array_job_id().unwrap_or(&job.job_id);
+        let args = submit_command(array_job_id)
+            .and_then(|command| resubmit_args(&command, indices))
+            .ok_or_else(|| {
+                anyhow!(
+                    "no sbatch command recorded for {} that can be rerun, resubmit it by hand",
+                    array_job_id
+                )
+            })?;
+        let output = std::process::Command::new("sbatch")
+            .args(args)
+            .current_dir(&job.work_dir)
+            .output()?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "sbatch failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        // --parsable prints "<job_id>[;<cluster>]"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let new_array_job_id = stdout.trim().split(';').next().unwrap_or_default();
+        Ok(Resubmission {
+            original: job.array_job_id().unwrap_or(&job.job_id).to_string(),
+            new: new_array_job_id.to_string(),
+            indices: indices.to_string(),
+        })
+    }
+
     fn run(&mut self) {
         // listen on the recv channel for a job handling instruction
         loop {
@@ -162,14 +875,22 @@ impl SlurmJobControl {
                     match msg {
                         Ok(msg) => {
                             match msg {
-                                JobControlMessage::CancelJob(job_id) => {
-                                    let rtn = self.cancel_job(&job_id);
+                                JobControlMessage::CancelJob(job_ids) => {
+                                    let rtn = self.cancel_job(&job_ids);
                                     self.send.send(AppMessage::JobCancelled(rtn)).unwrap();
                                 }
                                 JobControlMessage::RequeueJob(slurm_job) => {
                                     let rtn = self.requeue_job(&slurm_job);
                                     self.send.send(AppMessage::JobRequeued(rtn)).unwrap();
                                 }
+                                JobControlMessage::SignalJob(job_id, signal) => {
+                                    let rtn = self.signal_job(&job_id, signal.as_deref());
+                                    self.send.send(AppMessage::JobSignalled(rtn)).unwrap();
+                                }
+                                JobControlMessage::ResubmitTasks(slurm_job, indices) => {
+                                    let rtn = self.resubmit_tasks(&slurm_job, &indices);
+                                    self.send.send(AppMessage::TasksResubmitted(rtn)).unwrap();
+                                }
                             }
                         }
                         Err(_) => {
@@ -194,3 +915,82 @@ impl SlurmJobControlHandle {
         Self {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compress_ranges_joins_consecutive_indices() {
+        assert_eq!(compress_ranges(&[3, 17, 42, 43, 44, 45]), "3,17,42-45");
+        assert_eq!(compress_ranges(&[45, 3, 44, 42, 43, 3]), "3,42-45");
+        assert_eq!(compress_ranges(&[0]), "0");
+        assert_eq!(compress_ranges(&[]), "");
+    }
+
+    #[test]
+    fn expand_hostlist_keeps_padding_and_splits_outside_brackets() {
+        assert_eq!(
+            expand_hostlist("node[012-014,020],gpu01"),
+            ["node012", "node013", "node014", "node020", "gpu01"]
+        );
+        assert_eq!(
+            expand_hostlist("r[1-2]n[01-02]"),
+            ["r1n01", "r1n02", "r2n01", "r2n02"]
+        );
+        assert_eq!(expand_hostlist("gpu01, gpu02"), ["gpu01", "gpu02"]);
+    }
+
+    #[test]
+    fn expand_hostlist_edge_cases() {
+        assert!(expand_hostlist("").is_empty());
+        assert!(expand_hostlist("None assigned").is_empty());
+        assert!(expand_hostlist("(null)").is_empty());
+        // backwards ranges are invalid in slurm and expand to nothing
+        assert!(expand_hostlist("node[05-03]").is_empty());
+        // brackets don't nest, a range that isn't numbers expands to nothing
+        assert!(expand_hostlist("node[1-[2]]").is_empty());
+        assert_eq!(expand_hostlist("node[01-02"), ["node[01-02"]);
+    }
+
+    #[test]
+    fn resubmit_args_replace_the_array() {
+        let args = |command: &str| resubmit_args(command, "3,5-7").map(|a| a.join(" "));
+        assert_eq!(
+            args("sbatch --array=0-99%10 -J train run.sh --lr 0.1").as_deref(),
+            Some("--parsable --array=3,5-7 -J train run.sh --lr 0.1")
+        );
+        assert_eq!(
+            args("/usr/bin/sbatch -a 0-9 --parsable --job-name train --hold run.sh").as_deref(),
+            Some("--parsable --array=3,5-7 --job-name train --hold run.sh")
+        );
+        assert_eq!(
+            args("sbatch -a0-9 -p gpu run.sh").as_deref(),
+            Some("--parsable --array=3,5-7 -p gpu run.sh")
+        );
+        assert_eq!(args("srun run.sh"), None);
+        assert_eq!(args(""), None);
+        let args = |command: &str| resubmit_args(command, "3");
+        assert_eq!(
+            args(r#"sbatch -a 0-9 -J "my job" --wrap="python train.py --lr 1e-3""#).unwrap(),
+            [
+                "--parsable",
+                "--array=3",
+                "-J",
+                "my job",
+                "--wrap=python train.py --lr 1e-3"
+            ]
+        );
+        assert_eq!(
+            args(r#"sbatch --comment='it'\''s "quoted"' run\ me.sh a\"b"#).unwrap(),
+            [
+                "--parsable",
+                "--array=3",
+                r#"--comment=it's "quoted""#,
+                "run me.sh",
+                r#"a"b"#
+            ]
+        );
+        assert_eq!(args(r#"sbatch --wrap="python train.py"#), None);
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    thread,
+    time::Duration,
+};
+
+use crossbeam::{
+    channel::{tick, unbounded, Receiver, RecvError, Sender},
+    select,
+};
+
+use crate::app::AppMessage;
+use crate::debounce::{Debounce, MIN_REFRESH_GAP};
+use crate::slurm::{parse_duration, parse_mem};
+
+enum StatWatcherMessage {
+    Jobs(Vec<String>),
+}
+
+/// One sample of sstat metrics for a running job, summed over its running steps.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JobStats {
+    // seconds of cpu time
+    pub ave_cpu: f64,
+    // all memory and disk values are in bytes
+    pub ave_rss: f64,
+    pub max_rss: f64,
+    pub max_disk_read: f64,
+    pub max_disk_write: f64,
+    pub ave_vm_size: f64,
+}
+
+struct StatWatcher {
+    app: Sender<AppMessage>,
+    receiver: Receiver<StatWatcherMessage>,
+    interval: Duration,
+    jobs: Vec<String>,
+    debounce: Debounce,
+}
+
+impl StatWatcher {
+    fn new(
+        app: Sender<AppMessage>,
+        receiver: Receiver<StatWatcherMessage>,
+        interval: Duration,
+    ) -> Self {
+        Self {
+            app,
+            receiver,
+            interval,
+            jobs: Vec::new(),
+            debounce: Debounce::new(MIN_REFRESH_GAP),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), RecvError> {
+        let ticker = tick(self.interval);
+        loop {
+            select! {
+                recv(self.receiver) -> msg => {
+                    match msg? {
+                        StatWatcherMessage::Jobs(jobs) => {
+                            self.jobs = jobs;
+                            if self.debounce.request() {
+                                self.refresh();
+                            }
+                        }
+                    }
+                }
+                recv(self.debounce.timer()) -> _ => self.refresh(),
+                recv(ticker) -> _ => self.refresh(),
+            }
+        }
+    }
+
+    fn refresh(&mut self) {
+        // an empty refresh serves a pending request too, or its timer would keep firing
+        self.debounce.refreshed();
+        if self.jobs.is_empty() {
+            return;
+        }
+        // skip this refresh if sstat can't be run, the next tick tries again.  Its exit status
+        // isn't checked, one of the jobs having finished fails it while the rest are still listed
+        let Some(output) = std::process::Command::new("sstat")
+            .args(["--allsteps", "--noheader", "--parsable2", "-j"])
+            .arg(self.jobs.join(","))
+            .arg("--format=JobID,AveCPU,AveRSS,MaxRSS,MaxDiskRead,MaxDiskWrite,AveVMSize")
+            .output()
+            .ok()
+        else {
+            return;
+        };
+        let stats = parse_sstat(&String::from_utf8_lossy(&output.stdout));
+        self.app.send(AppMessage::JobStats(stats)).unwrap();
+    }
+}
+
+/// A sample for each job in `sstat --parsable2` output, its steps' values added together.
+pub fn parse_sstat(output: &str) -> HashMap<String, JobStats> {
+    let mut stats: HashMap<String, JobStats> = HashMap::new();
+    output.lines().for_each(|line| {
+        let parts = line.split('|').collect::<Vec<&str>>();
+        if parts.len() < 7 {
+            return;
+        }
+        let job_id = parts[0].split('.').next().unwrap_or_default().to_string();
+        let job = stats.entry(job_id).or_default();
+        job.ave_cpu += parse_duration(parts[1]).unwrap_or(0.);
+        job.ave_rss += parse_bytes(parts[2]);
+        job.max_rss = job.max_rss.max(parse_bytes(parts[3]));
+        job.max_disk_read += parse_bytes(parts[4]);
+        job.max_disk_write += parse_bytes(parts[5]);
+        job.ave_vm_size += parse_bytes(parts[6]);
+    });
+    stats
+}
+
+/// The increase of a cumulative value, e.g. cpu time or disk io, between each pair of samples.
+pub fn sample_deltas(history: &VecDeque<JobStats>, f: fn(&JobStats) -> f64) -> Vec<u64> {
+    history
+        .iter()
+        .zip(history.iter().skip(1))
+        .map(|(a, b)| (f(b) - f(a)).max(0.) as u64)
+        .collect()
+}
+
+// sstat prints unitless values in bytes, unlike the megabytes parse_mem assumes
+fn parse_bytes(value: &str) -> f64 {
+    match value.chars().last() {
+        Some(c) if c.is_ascii_digit() => value.parse().unwrap_or(0.),
+        _ => parse_mem(value).unwrap_or(0.),
+    }
+}
+
+#[derive(Debug)]
+pub struct StatWatcherHandle {
+    sender: Sender<StatWatcherMessage>,
+    jobs: Vec<String>,
+}
+
+impl StatWatcherHandle {
+    pub fn new(app: Sender<AppMessage>, interval: Duration) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = StatWatcher::new(app, receiver, interval);
+        thread::spawn(move || actor.run());
+        Self {
+            sender,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Set the running jobs to poll sstat for, only the selected and pinned jobs should be
+    /// passed here.
+    pub fn set_jobs(&mut self, jobs: Vec<String>) {
+        if self.jobs != jobs {
+            self.jobs = jobs.clone();
+            self.sender.send(StatWatcherMessage::Jobs(jobs)).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_are_added_up() {
+        let output = "123.batch|00:01:00|1024K|2M|10M|1M|4G\n\
+123.0|01:00:00|3M|5M|20M|1M|1G\n\
+124.0|00:00:30|100|100|0|0|0\n\
+short|line\n";
+        let stats = parse_sstat(output);
+        assert_eq!(stats.len(), 2);
+        let job = &stats["123"];
+        assert_eq!(job.ave_cpu, 3660.);
+        assert_eq!(job.ave_rss, 4. * 1024. * 1024.);
+        assert_eq!(job.max_rss, 5. * 1024. * 1024.);
+        assert_eq!(job.max_disk_read, 30. * 1024. * 1024.);
+        assert_eq!(job.ave_vm_size, 5. * 1024. * 1024. * 1024.);
+        assert_eq!(stats["124"].ave_rss, 100.);
+    }
+
+    #[test]
+    fn deltas_between_samples() {
+        let history: VecDeque<JobStats> = [10., 25., 20., 40.]
+            .into_iter()
+            .map(|ave_cpu| JobStats {
+                ave_cpu,
+                ..JobStats::default()
+            })
+            .collect();
+        // a step finishing makes the total drop, which isn't negative usage
+        assert_eq!(sample_deltas(&history, |s| s.ave_cpu), [15, 0, 20]);
+        assert!(sample_deltas(&VecDeque::new(), |s| s.ave_cpu).is_empty());
+    }
+}
@@ -1,23 +1,38 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
 
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     prelude::Rect,
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, BorderType, Borders, Clear, Paragraph, Row, Table},
+    widgets::{
+        block::Title, Block, BorderType, Borders, Clear, Gauge, Paragraph, Row, Sparkline, Table,
+        Wrap,
+    },
     Frame,
 };
 
-use crate::app::{App, Focus, RightPanelFocus};
+use crate::{
+    analysis::exclude_option,
+    app::{App, ArrayJob, Focus, JobRow, Prompt, PromptKind, RightPanelFocus, View},
+    cluster::NodeState,
+    columns::Column,
+    node_watcher::NodeDetail,
+    pending::explain_reason,
+    search::{SearchField, SearchHit},
+    slurm::{
+        expand_hostlist, format_duration, format_mem, parse_duration, parse_mem, seconds_until,
+        SlurmJob,
+    },
+    stat_watcher::{sample_deltas, JobStats},
+};
 
 /// Renders the user interface widgets.
 pub fn render(app: &mut App, frame: &mut Frame) {
     let blue_style = Style::default().fg(Color::LightBlue);
     let light_green_style = Style::default().fg(Color::LightGreen);
-    let red_style = Style::default().fg(Color::LightRed);
     let orange_style = Style::default().fg(Color::Yellow);
-    let white_style = Style::default().fg(Color::White);
     let purple_style = Style::default().fg(Color::Magenta);
 
     let chunks = Layout::default()
@@ -27,165 +42,66 @@ pub fn render(app: &mut App, frame: &mut Frame) {
 
     let bottom_bar_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(80), Constraint::Percentage(20)])
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
         .split(chunks[1]);
 
-    let subchunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
-        .split(chunks[0]);
-
-    let rhs_subchunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Max(11),
-            // Constraint::Length(3),
-            Constraint::Max(86),
-        ])
-        .split(subchunks[1]);
-
-    let job_status_map = HashMap::from([
-        ("F", red_style),
-        ("PD", orange_style),
-        ("R", light_green_style),
-        ("CD", light_green_style),
-        ("CA", orange_style),
-        ("TO", light_green_style),
-        ("PR", orange_style),
-        ("NF", orange_style),
-        ("RV", orange_style),
-        ("S", orange_style),
-    ]);
-
-    // construct detailed job info
-    let mut job_details = Vec::new();
-    let selected_job = &app.slurm_jobs.items.get(app.selected_index).clone();
-    if let Some(selected_job) = selected_job {
-        job_details.push(Row::new(vec![
-            Span::styled("STATE".to_string(), blue_style),
-            Span::styled(
-                selected_job.state.to_owned(),
-                *job_status_map
-                    .get(&selected_job.state.as_str())
-                    .unwrap_or(&white_style),
-            ),
-        ]));
-        job_details.push(Row::new(vec![
-            Span::styled("JOB ID".to_string(), blue_style),
-            Span::styled(selected_job.job_id.to_owned(), white_style),
-        ]));
-        job_details.push(Row::new(vec![
-            Span::styled("JOB NAME".to_string(), blue_style),
-            Span::styled(selected_job.job_name.to_owned(), white_style),
-        ]));
-        job_details.push(Row::new(vec![
-            Span::styled("NODE".to_string(), blue_style),
-            Span::styled(selected_job.node_list.to_owned(), white_style),
-        ]));
-        job_details.push(Row::new(vec![
-            Span::styled("WORK DIR".to_string(), blue_style),
-            Span::styled(selected_job.work_dir.to_owned(), white_style),
-        ]));
-        job_details.push(Row::new(vec![
-            Span::styled("ACCOUNT".to_string(), blue_style),
-            Span::styled(selected_job.account.to_owned(), white_style),
-        ]));
-        job_details.push(Row::new(vec![
-            Span::styled("SUBMIT".to_string(), blue_style),
-            Span::styled(selected_job.submit.to_owned(), white_style),
-        ]));
-        job_details.push(Row::new(vec![
-            Span::styled("START".to_string(), blue_style),
-            Span::styled(selected_job.start.to_owned(), white_style),
-        ]));
-        job_details.push(Row::new(vec![
-            Span::styled("ELAPSED".to_string(), blue_style),
-            Span::styled(
-                selected_job.elapsed_time.to_owned() + " / " + &selected_job.time_limit,
-                white_style,
-            ),
-        ]));
+    match app.view {
+        View::Jobs => render_jobs(app, frame, chunks[0]),
+        View::Cluster => render_cluster(app, frame, chunks[0]),
+        View::Nodes => render_node_failures(app, frame, chunks[0]),
+        View::Accounts => render_accounts(app, frame, chunks[0]),
     }
 
-    let details = Table::new(
-        job_details,
-        &[Constraint::Length(10), Constraint::Percentage(95)],
-    )
-    .block(
-        Block::default()
-            .title("Job details")
-            .title_alignment(Alignment::Left)
-            .borders(Borders::ALL)
-            .border_type(BorderType::Rounded),
-    );
-
-    frame.render_widget(details, rhs_subchunks[0]);
-
-    let jobs_as_rows = app
-        .slurm_jobs
-        .items
-        .iter()
-        .fold(Vec::new(), |mut acc, job| {
-            let status_style = job_status_map
-                .get(&job.state.as_str())
-                .unwrap_or(&red_style);
-            let row = Row::new(vec![
-                Span::styled(job.job_id.clone(), blue_style),
-                Span::styled(job.state.clone(), *status_style),
-                Span::styled(job.job_name.clone(), white_style),
-            ]);
-            acc.push(row);
-            acc
-        });
-
-    let (job_style, output_style) = match app.focus {
-        Focus::JobList => (
-            Style::default().fg(Color::Green),
-            Style::default().fg(Color::White),
-        ),
-        Focus::Output => (
-            Style::default().fg(Color::White),
-            Style::default().fg(Color::Green),
-        ),
-    };
-
-    let table = Table::new(
-        jobs_as_rows,
-        &[
-            Constraint::Percentage(30),
-            Constraint::Percentage(5),
-            Constraint::Percentage(65),
+    let help_options = match app.view {
+        View::Jobs => vec![
+            ("q/⌃c", "quit"),
+            ("⏶/⏷", "navigate"),
+            ("t/b", "top/bottom"),
+            ("⇧", "fast scroll"),
+            ("esc", "cancel"),
+            ("c", "cancel job"),
+            ("r", "requeue job"),
+            ("f", "toggle job filter"),
+            ("p", "pin metrics"),
+            ("w", "watch job"),
+            ("⏎", "expand"),
+            ("space", "mark"),
+            ("m", "array matrix"),
+            ("R", "resubmit failed tasks"),
+            ("X", "cancel with dependents"),
+            ("j", "cycle panel"),
+            ("T", "time window"),
+            ("[/]", "shrink/grow window"),
+            ("F", "query"),
+            ("/", "find job"),
+            (":filter", "filter jobs"),
+            ("s/S", "sort column/order"),
+            (":columns", "choose columns"),
+            ("1-4", "jobs/cluster/nodes/accounts"),
+            // ("o", "toggle stdout/stderr"),
         ],
-    )
-    .block(
-        Block::default()
-            .title("SLURM Job List")
-            .title_alignment(Alignment::Left)
-            .borders(Borders::ALL)
-            .border_type(BorderType::Rounded)
-            .style(job_style),
-    )
-    .highlight_style(
-        Style::default()
-            .bg(Color::Green)
-            .fg(Color::Black)
-            .add_modifier(Modifier::BOLD),
-    );
-
-    frame.render_stateful_widget(table, subchunks[0], &mut app.slurm_jobs.state);
-
-    let help_options = vec![
-        ("q/⌃c", "quit"),
-        ("⏶/⏷", "navigate"),
-        ("t/b", "top/bottom"),
-        ("⇧", "fast scroll"),
-        ("esc", "cancel"),
-        ("c", "cancel job"),
-        ("r", "requeue job"),
-        ("f", "toggle job filter"),
-        ("j", "toggle output"),
-        // ("o", "toggle stdout/stderr"),
-    ];
+        View::Cluster => vec![
+            ("q/⌃c", "quit"),
+            ("⏶/⏷", "navigate"),
+            ("⏎", "show nodes"),
+            ("esc", "back to partitions"),
+            ("1-4", "jobs/cluster/nodes/accounts"),
+        ],
+        // the failure analysis keeps the jobs it has seen, a wider window or query adds to it
+        View::Nodes => vec![
+            ("q/⌃c", "quit"),
+            ("⏶/⏷", "navigate"),
+            ("T", "time window"),
+            ("[/]", "shrink/grow window"),
+            ("F", "query"),
+            ("1-4", "jobs/cluster/nodes/accounts"),
+        ],
+        View::Accounts => vec![
+            ("q/⌃c", "quit"),
+            ("⏶/⏷", "navigate"),
+            ("1-4", "jobs/cluster/nodes/accounts"),
+        ],
+    };
 
     let help = Line::from(
         help_options
@@ -201,13 +117,19 @@ pub fn render(app: &mut App, frame: &mut Frame) {
             }),
     );
 
-    let help = Paragraph::new(help).block(
-        Block::default()
-            .title("Commands")
-            .title_alignment(Alignment::Left)
-            .borders(Borders::ALL)
-            .border_type(BorderType::Rounded),
-    );
+    let mut help_block = Block::default()
+        .title("Commands")
+        .title_alignment(Alignment::Left)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    // the result of the last action goes in the top right corner of the commands bar
+    if let Some(message) = &app.status_message {
+        help_block = help_block.title(
+            Title::from(Span::styled(format!(" {} ", message), orange_style))
+                .alignment(Alignment::Right),
+        );
+    }
+    let help = Paragraph::new(help).block(help_block);
     frame.render_widget(help, bottom_bar_chunks[0]);
 
     let mut status_info = Vec::new();
@@ -223,6 +145,12 @@ pub fn render(app: &mut App, frame: &mut Frame) {
             status_info.push(Span::styled("All", orange_style));
         }
     };
+    if !app.filter.is_empty() {
+        status_info.push(Span::raw(" | "));
+        status_info.push(Span::styled("Filter", purple_style));
+        status_info.push(Span::raw(": "));
+        status_info.push(Span::styled(app.filter.to_string(), orange_style));
+    }
     match app.right_panel_focus {
         RightPanelFocus::Output => {
             status_info.push(Span::raw(" | "));
@@ -236,6 +164,50 @@ pub fn render(app: &mut App, frame: &mut Frame) {
             status_info.push(Span::raw(": "));
             status_info.push(Span::styled("Jobscript", orange_style));
         }
+        RightPanelFocus::Efficiency => {
+            status_info.push(Span::raw(" | "));
+            status_info.push(Span::styled("Output", purple_style));
+            status_info.push(Span::raw(": "));
+            status_info.push(Span::styled("Efficiency", orange_style));
+        }
+        RightPanelFocus::Metrics => {
+            status_info.push(Span::raw(" | "));
+            status_info.push(Span::styled("Output", purple_style));
+            status_info.push(Span::raw(": "));
+            status_info.push(Span::styled("Metrics", orange_style));
+        }
+        RightPanelFocus::Dependencies => {
+            status_info.push(Span::raw(" | "));
+            status_info.push(Span::styled("Output", purple_style));
+            status_info.push(Span::raw(": "));
+            status_info.push(Span::styled("Dependencies", orange_style));
+        }
+        RightPanelFocus::Nodes => {
+            status_info.push(Span::raw(" | "));
+            status_info.push(Span::styled("Output", purple_style));
+            status_info.push(Span::raw(": "));
+            status_info.push(Span::styled("Nodes", orange_style));
+        }
+        RightPanelFocus::Hooks => {
+            status_info.push(Span::raw(" | "));
+            status_info.push(Span::styled("Output", purple_style));
+            status_info.push(Span::raw(": "));
+            status_info.push(Span::styled("Hooks", orange_style));
+        }
+    }
+
+    status_info.push(Span::raw(" | "));
+    status_info.push(Span::styled("Window", purple_style));
+    status_info.push(Span::raw(": "));
+    status_info.push(Span::styled(
+        app.time_window().to_string(),
+        light_green_style,
+    ));
+    if !app.query().is_empty() {
+        status_info.push(Span::raw(" | "));
+        status_info.push(Span::styled("Query", purple_style));
+        status_info.push(Span::raw(": "));
+        status_info.push(Span::styled(app.query().to_string(), orange_style));
     }
 
     let status_info = Paragraph::new(Line::from(status_info)).block(
@@ -247,72 +219,10 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     );
     frame.render_widget(status_info, bottom_bar_chunks[1]);
 
-    let output = match app.right_panel_focus {
-        RightPanelFocus::Output => Table::new(
-            app.job_output
-                .items
-                .iter()
-                .fold(Vec::new(), |mut acc, line| {
-                    acc.push(Row::new(vec![Span::styled(line, white_style)]));
-                    acc
-                }),
-            &[Constraint::Percentage(100)],
-        )
-        .block(
-            Block::default()
-                .title("Output")
-                .title_alignment(Alignment::Left)
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .style(output_style),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(Color::Blue)
-                .fg(Color::Black)
-                .add_modifier(Modifier::BOLD),
-        ),
-        RightPanelFocus::JobScript => Table::new(
-            app.job_script
-                .items
-                .iter()
-                .fold(Vec::new(), |mut acc, line| {
-                    acc.push(Row::new(vec![Span::styled(line, white_style)]));
-                    acc
-                }),
-            &[Constraint::Percentage(100)],
-        )
-        .block(
-            Block::default()
-                .title("Job Script")
-                .title_alignment(Alignment::Left)
-                .borders(Borders::ALL)
-                .border_type(BorderType::Rounded)
-                .style(output_style),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(Color::Blue)
-                .fg(Color::Black)
-                .add_modifier(Modifier::BOLD),
-        ),
-    };
-    match app.right_panel_focus {
-        RightPanelFocus::JobScript => {
-            frame.render_stateful_widget(output, rhs_subchunks[1], &mut app.job_script.state);
-        }
-        RightPanelFocus::Output => {
-            frame.render_stateful_widget(output, rhs_subchunks[1], &mut app.job_output.state);
-        }
-    };
-
     // if we are in the process of cancelling a job, render a central box over the top,
     if app.cancelling {
         let area = centered_rect(30, 10, frame.size());
-        let text = format!(
-            "Cancelling job: {}",
-            app.slurm_jobs.items[app.selected_index].job_id
-        );
+        let text = format!("Cancelling job: {}", app.cancel_targets.join(", "));
         let cancel_box = Paragraph::new(text)
             .block(
                 Block::default()
@@ -327,10 +237,7 @@ pub fn render(app: &mut App, frame: &mut Frame) {
     }
     if app.requeueing {
         let area = centered_rect(30, 10, frame.size());
-        let text = format!(
-            "Requeueing job: {}",
-            app.slurm_jobs.items[app.selected_index].job_id
-        );
+        let text = format!("Requeueing job: {}", app.requeue_target);
         let cancel_box = Paragraph::new(text)
             .block(
                 Block::default()
@@ -343,6 +250,1677 @@ pub fn render(app: &mut App, frame: &mut Frame) {
         frame.render_widget(Clear, area);
         frame.render_widget(cancel_box, area);
     }
+    if let Some(prompt) = &app.prompt {
+        render_prompt(prompt, &app.search_hits, app.search_selected, frame);
+    }
+}
+
+/// A box over the middle of the screen with the line being typed in, and the ranked matches
+/// below it for the fuzzy finder.
+fn render_prompt(prompt: &Prompt, hits: &[SearchHit], selected: usize, frame: &mut Frame) {
+    let (title, hint) = match prompt.kind {
+        PromptKind::TimeWindow => (
+            " Time window ",
+            "e.g. 90m, 3d, 2024-03-01..2024-03-05 or 2024-03-01 12:00..now",
+        ),
+        PromptKind::Query => (
+            " Query slurm for ",
+            "e.g. state:F,TO partition:gpu account:proj name:sweep_* id:1200-1300 qos:long, \
+            empty for all jobs",
+        ),
+        PromptKind::Command => (
+            " Command ",
+            "filter state:F,TO partition:gpu name~\"sweep_*\" elapsed>2h node:gpu0[1-4], \
+            empty to clear, ⏶/⏷ for earlier filters; columns id,state,name,elapsed; \
+            sort elapsed desc",
+        ),
+        PromptKind::Search => (
+            " Find job ",
+            "matches id, name, partition and work dir, ⏶/⏷ to choose, ⏎ to jump",
+        ),
+    };
+    let mut lines = vec![
+        Line::from(vec![
+            Span::raw(match prompt.kind {
+                PromptKind::Command => ":",
+                _ => "> ",
+            }),
+            Span::raw(prompt.input.as_str()),
+            Span::styled("█", Style::default().fg(Color::Gray)),
+        ]),
+        Line::from(Span::styled(hint, Style::default().fg(Color::DarkGray))),
+    ];
+    if let Some(error) = &prompt.error {
+        lines.push(Line::from(Span::styled(
+            error.as_str(),
+            Style::default().fg(Color::Red),
+        )));
+    }
+    let area = match prompt.kind {
+        PromptKind::Search => centered_rect(50, 50, frame.size()),
+        _ => centered_rect(50, 15, frame.size()),
+    };
+    if prompt.kind == PromptKind::Search {
+        let matched_style = Style::default()
+            .fg(Color::Yellow)
+            .add_modifier(Modifier::BOLD);
+        // keep the chosen match in view
+        let room = (area.height as usize).saturating_sub(lines.len() + 2);
+        let skip = (selected + 1).saturating_sub(room);
+        for (i, hit) in hits.iter().enumerate().skip(skip).take(room) {
+            let marker = match i == selected {
+                true => "▶ ",
+                false => "  ",
+            };
+            let mut spans = vec![Span::raw(marker)];
+            let white_style = Style::default().fg(Color::White);
+            match hit.field {
+                SearchField::Id => {
+                    spans.extend(highlight(
+                        &hit.id,
+                        &hit.positions,
+                        white_style,
+                        matched_style,
+                    ));
+                    spans.push(Span::styled(format!("  {}", hit.name), white_style));
+                }
+                SearchField::Name => {
+                    spans.push(Span::styled(format!("{}  ", hit.id), white_style));
+                    spans.extend(highlight(
+                        &hit.name,
+                        &hit.positions,
+                        white_style,
+                        matched_style,
+                    ));
+                }
+                SearchField::Partition | SearchField::WorkDir => {
+                    let gray_style = Style::default().fg(Color::Gray);
+                    spans.push(Span::styled(
+                        format!("{}  {}  ", hit.id, hit.name),
+                        white_style,
+                    ));
+                    spans.extend(highlight(
+                        &hit.text,
+                        &hit.positions,
+                        gray_style,
+                        matched_style,
+                    ));
+                }
+            }
+            lines.push(Line::from(spans));
+        }
+        if hits.is_empty() && !prompt.input.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "no job matches",
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+    }
+    let prompt_box = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(title)
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .style(Style::default().fg(Color::LightBlue)),
+        )
+        .wrap(Wrap { trim: false });
+    frame.render_widget(Clear, area);
+    frame.render_widget(prompt_box, area);
+}
+
+/// Spans of the text with the characters at the given positions picked out.
+fn highlight<'a>(text: &str, positions: &[usize], style: Style, matched: Style) -> Vec<Span<'a>> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    for (i, c) in text.chars().enumerate() {
+        let is_matched = positions.contains(&i);
+        if is_matched != run_matched && !run.is_empty() {
+            let style = if run_matched { matched } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_matched = is_matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        spans.push(Span::styled(run, if run_matched { matched } else { style }));
+    }
+    spans
+}
+
+/// The job list, details of the selected job and the right hand panel.
+fn render_jobs(app: &mut App, frame: &mut Frame, area: Rect) {
+    let blue_style = Style::default().fg(Color::LightBlue);
+    let light_green_style = Style::default().fg(Color::LightGreen);
+    let red_style = Style::default().fg(Color::LightRed);
+    let orange_style = Style::default().fg(Color::Yellow);
+    let white_style = Style::default().fg(Color::White);
+    let purple_style = Style::default().fg(Color::Magenta);
+
+    // the list takes a quarter of the screen, or more if its columns need it, but always leaves
+    // room for the details
+    let needed = app
+        .config
+        .columns
+        .iter()
+        .map(|c| c.min_width() + 1)
+        .sum::<u16>()
+        + 2;
+    let list_width = needed.max(area.width / 4).min(area.width * 3 / 5);
+    let subchunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(list_width), Constraint::Min(0)])
+        .split(area);
+
+    let job_status_map = job_status_map();
+
+    // construct detailed job info
+    let mut job_details = Vec::new();
+    // while the array matrix is open the details follow its cursor rather than the table
+    let selected_row = match app.array_matrix {
+        Some(_) => app.selected_job().cloned().map(JobRow::Job),
+        None => app.slurm_jobs.items.get(app.selected_index).cloned(),
+    };
+    let selected_job = &app.selected_job().cloned();
+    if let Some(JobRow::Array(array)) = &selected_row {
+        job_details.push(Row::new(vec![
+            Span::styled("STATE".to_string(), blue_style),
+            Span::styled(
+                array.state_summary(),
+                *job_status_map.get(array.state()).unwrap_or(&white_style),
+            ),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("ARRAY ID".to_string(), blue_style),
+            Span::styled(array.array_job_id.to_owned(), white_style),
+        ]));
+        if let Some(task) = array.tasks.first() {
+            job_details.push(Row::new(vec![
+                Span::styled("JOB NAME".to_string(), blue_style),
+                Span::styled(task.job_name.to_owned(), white_style),
+            ]));
+            job_details.push(Row::new(vec![
+                Span::styled("PARTITION".to_string(), blue_style),
+                Span::styled(task.partition.to_owned(), white_style),
+            ]));
+            job_details.push(Row::new(vec![
+                Span::styled("WORK DIR".to_string(), blue_style),
+                Span::styled(task.work_dir.to_owned(), white_style),
+            ]));
+        }
+        let indices = array
+            .tasks
+            .iter()
+            .filter_map(|t| t.array_task_id())
+            .collect::<Vec<u64>>();
+        // link arrays resubmitted from this one, and this one to the array it was resubmitted from
+        for resubmission in &app.resubmissions {
+            if resubmission.original == array.array_job_id {
+                job_details.push(Row::new(vec![
+                    Span::styled("RESUBMIT".to_string(), blue_style),
+                    Span::styled(
+                        format!("tasks {} as {}", resubmission.indices, resubmission.new),
+                        orange_style,
+                    ),
+                ]));
+            }
+            if resubmission.new == array.array_job_id {
+                job_details.push(Row::new(vec![
+                    Span::styled("RESUBMIT".to_string(), blue_style),
+                    Span::styled(
+                        format!(
+                            "of tasks {} of {}",
+                            resubmission.indices, resubmission.original
+                        ),
+                        orange_style,
+                    ),
+                ]));
+            }
+        }
+        job_details.push(Row::new(vec![
+            Span::styled("TASKS".to_string(), blue_style),
+            Span::styled(
+                format!(
+                    "{} ({}-{})",
+                    array.tasks.len(),
+                    indices.iter().min().unwrap_or(&0),
+                    indices.iter().max().unwrap_or(&0)
+                ),
+                white_style,
+            ),
+        ]));
+    } else if let Some(JobRow::Step(job, step)) = &selected_row {
+        job_details.push(Row::new(vec![
+            Span::styled("STATE".to_string(), blue_style),
+            Span::styled(
+                step.state.to_owned(),
+                *job_status_map
+                    .get(&step.state.as_str())
+                    .unwrap_or(&white_style),
+            ),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("STEP ID".to_string(), blue_style),
+            Span::styled(format!("{}.{}", job.job_id, step.step_id), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("STEP NAME".to_string(), blue_style),
+            Span::styled(step.step_name.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("NODE".to_string(), blue_style),
+            Span::styled(step.node_list.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("ELAPSED".to_string(), blue_style),
+            Span::styled(step.elapsed_time.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("EXIT CODE".to_string(), blue_style),
+            Span::styled(step.exit_code.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("CPU".to_string(), blue_style),
+            Span::styled(
+                format!("TotalCPU {} on {} cpus", step.total_cpu, step.alloc_cpus),
+                white_style,
+            ),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("MAX RSS".to_string(), blue_style),
+            Span::styled(
+                parse_mem(&step.max_rss).map(format_mem).unwrap_or_default(),
+                white_style,
+            ),
+        ]));
+    } else if let Some(selected_job) = selected_job {
+        job_details.push(Row::new(vec![
+            Span::styled("STATE".to_string(), blue_style),
+            Span::styled(
+                selected_job.state.to_owned(),
+                *job_status_map
+                    .get(&selected_job.state.as_str())
+                    .unwrap_or(&white_style),
+            ),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("JOB ID".to_string(), blue_style),
+            Span::styled(selected_job.job_id.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("JOB NAME".to_string(), blue_style),
+            Span::styled(selected_job.job_name.to_owned(), white_style),
+        ]));
+        // a compressed hostlist hides how many nodes the job is spread over
+        let node_count = expand_hostlist(&selected_job.node_list).len();
+        let node_list = match node_count {
+            0 | 1 => selected_job.node_list.to_owned(),
+            n => format!("{} ({} nodes)", selected_job.node_list, n),
+        };
+        job_details.push(Row::new(vec![
+            Span::styled("NODE".to_string(), blue_style),
+            Span::styled(node_list, white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("WORK DIR".to_string(), blue_style),
+            Span::styled(selected_job.work_dir.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("ACCOUNT".to_string(), blue_style),
+            Span::styled(selected_job.account.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("SUBMIT".to_string(), blue_style),
+            Span::styled(selected_job.submit.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("START".to_string(), blue_style),
+            Span::styled(selected_job.start.to_owned(), white_style),
+        ]));
+        job_details.push(Row::new(vec![
+            Span::styled("ELAPSED".to_string(), blue_style),
+            Span::styled(
+                selected_job.elapsed_time.to_owned() + " / " + &selected_job.time_limit,
+                white_style,
+            ),
+        ]));
+        if let Some(age) = app.output_age(selected_job) {
+            let (last_output, style) = match app.is_stalled(selected_job) {
+                true => (format!("{}, stalled", format_age(age)), orange_style),
+                false => (format_age(age), white_style),
+            };
+            job_details.push(Row::new(vec![
+                Span::styled("LAST OUT".to_string(), blue_style),
+                Span::styled(last_output, style),
+            ]));
+        }
+        if selected_job.state == "PD" {
+            job_details.push(Row::new(vec![
+                Span::styled("REASON".to_string(), blue_style),
+                Span::styled(selected_job.reason.to_owned(), orange_style),
+            ]));
+            if let Some(reason) = explain_reason(&selected_job.reason) {
+                job_details.push(Row::new(vec![
+                    Span::styled("WHY".to_string(), blue_style),
+                    Span::styled(reason.explanation, white_style),
+                ]));
+                job_details.push(Row::new(vec![
+                    Span::styled("TRY".to_string(), blue_style),
+                    Span::styled(reason.suggestion, light_green_style),
+                ]));
+            }
+            if let Some(priority) = selected_job.priority {
+                let factors = priority
+                    .breakdown()
+                    .iter()
+                    .filter(|(_, value, _)| *value > 0)
+                    .map(|(name, value, share)| {
+                        format!("{} {} ({:.0}%)", name, value, share * 100.)
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                job_details.push(Row::new(vec![
+                    Span::styled("PRIORITY".to_string(), blue_style),
+                    Span::styled(format!("{} = {}", priority.total, factors), white_style),
+                ]));
+            }
+            if let Some(seconds) = seconds_until(&selected_job.est_start) {
+                // the estimate is only refreshed with the job list, so it can fall behind
+                let countdown = match seconds > 0 {
+                    true => format!("in {}", format_duration(seconds as f64)),
+                    false => "overdue".to_string(),
+                };
+                job_details.push(Row::new(vec![
+                    Span::styled("EST START".to_string(), blue_style),
+                    Span::styled(
+                        format!("{} ({})", selected_job.est_start, countdown),
+                        light_green_style,
+                    ),
+                ]));
+            }
+            if !selected_job.sched_nodes.is_empty() {
+                job_details.push(Row::new(vec![
+                    Span::styled("SCHED NODE".to_string(), blue_style),
+                    Span::styled(selected_job.sched_nodes.to_owned(), white_style),
+                ]));
+            }
+            if let Some((position, pending)) = selected_job.queue_position {
+                job_details.push(Row::new(vec![
+                    Span::styled("QUEUE POS".to_string(), blue_style),
+                    Span::styled(
+                        format!(
+                            "{} of {} pending in {}",
+                            position, pending, selected_job.partition
+                        ),
+                        white_style,
+                    ),
+                ]));
+            }
+        }
+        if selected_job.is_finished() {
+            let derived = match selected_job.derived_exit_code.as_str() {
+                "" | "0:0" => "".to_string(),
+                derived => format!(" (derived {})", derived),
+            };
+            job_details.push(Row::new(vec![
+                Span::styled("EXIT CODE".to_string(), blue_style),
+                Span::styled(selected_job.exit_code.to_owned() + &derived, white_style),
+            ]));
+            if !["", "None"].contains(&selected_job.reason.as_str()) {
+                job_details.push(Row::new(vec![
+                    Span::styled("REASON".to_string(), blue_style),
+                    Span::styled(selected_job.reason.to_owned(), white_style),
+                ]));
+            }
+            if !selected_job.steps.is_empty() {
+                job_details.push(Row::new(vec![
+                    Span::styled("STEPS".to_string(), blue_style),
+                    Span::styled(selected_job.step_summary(), white_style),
+                ]));
+            }
+            if let Some(diagnosis) = selected_job.diagnose() {
+                let diagnosis_style = match selected_job.state.as_str() {
+                    "CD" => light_green_style,
+                    _ => red_style,
+                };
+                job_details.push(Row::new(vec![
+                    Span::styled("DIAGNOSIS".to_string(), blue_style),
+                    Span::styled(diagnosis, diagnosis_style),
+                ]));
+            }
+            for (i, line) in app.error_lines.iter().enumerate() {
+                let label = if i == 0 { "STDERR" } else { "" };
+                job_details.push(Row::new(vec![
+                    Span::styled(label.to_string(), blue_style),
+                    Span::styled(line.to_owned(), orange_style),
+                ]));
+            }
+        }
+    }
+
+    let rhs_subchunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(job_details.len() as u16 + 2),
+            Constraint::Min(10),
+        ])
+        .split(subchunks[1]);
+
+    let details = Table::new(
+        job_details,
+        &[Constraint::Length(10), Constraint::Percentage(95)],
+    )
+    .block(
+        Block::default()
+            .title("Job details")
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+
+    frame.render_widget(details, rhs_subchunks[0]);
+
+    let search_hits = match &app.prompt {
+        Some(prompt) if prompt.kind == PromptKind::Search => app.search_hits.as_slice(),
+        _ => &[],
+    };
+    let matched_style = Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD);
+    let changed_style = Style::default()
+        .bg(Color::DarkGray)
+        .add_modifier(Modifier::BOLD);
+    let columns = &app.config.columns;
+    let jobs_as_rows = app
+        .slurm_jobs
+        .items
+        .iter()
+        .fold(Vec::new(), |mut acc, row| {
+            let cells = match row {
+                JobRow::Job(job) => {
+                    let status_style = job_status_map
+                        .get(&job.state.as_str())
+                        .unwrap_or(&red_style);
+                    // pick out what the fuzzy finder matched while it is open
+                    let hit = search_hits.iter().find(|hit| hit.id == job.job_id);
+                    let positions = |field: SearchField| match hit {
+                        Some(hit) if hit.field == field => hit.positions.as_slice(),
+                        _ => &[],
+                    };
+                    columns
+                        .iter()
+                        .map(|column| match column {
+                            Column::Id => {
+                                let expander = match (
+                                    job.steps.is_empty(),
+                                    app.expanded.contains(&job.job_id),
+                                ) {
+                                    (true, _) => "  ",
+                                    (false, true) => "▾ ",
+                                    (false, false) => "▸ ",
+                                };
+                                // tasks are listed under their array row
+                                let indent = match job.array_job_id() {
+                                    Some(_) => " ",
+                                    None => "",
+                                };
+                                // pinned jobs keep their live metrics polled, mark them in the list
+                                let pinned = match app.pinned.contains(&job.job_id) {
+                                    true => " *",
+                                    false => "",
+                                };
+                                let marked = match app.marked.contains(&job.job_id) {
+                                    true => " ✓",
+                                    false => "",
+                                };
+                                // only watched jobs notify unless the config says otherwise
+                                let watched = match app.watched.contains(&job.job_id) {
+                                    true => " ⚑",
+                                    false => "",
+                                };
+                                let mut id = vec![Span::styled(
+                                    format!("{}{}", indent, expander),
+                                    blue_style,
+                                )];
+                                id.extend(highlight(
+                                    &job.job_id,
+                                    positions(SearchField::Id),
+                                    blue_style,
+                                    matched_style,
+                                ));
+                                id.push(Span::styled(
+                                    format!("{}{}{}", pinned, marked, watched),
+                                    blue_style,
+                                ));
+                                Line::from(id)
+                            }
+                            // a running job that has gone quiet is picked out in the state too,
+                            // so it shows without the output column
+                            Column::State if app.is_stalled(job) => {
+                                Line::from(Span::styled(job.state.clone(), orange_style))
+                            }
+                            Column::State => {
+                                Line::from(Span::styled(job.state.clone(), *status_style))
+                            }
+                            Column::LastOutput => match app.output_age(job) {
+                                Some(age) if app.is_stalled(job) => Line::from(Span::styled(
+                                    format!("{} ⏸", format_age(age)),
+                                    orange_style,
+                                )),
+                                Some(age) => Line::from(Span::styled(format_age(age), white_style)),
+                                None => Line::default(),
+                            },
+                            Column::Name => Line::from(highlight(
+                                &job.job_name,
+                                positions(SearchField::Name),
+                                white_style,
+                                matched_style,
+                            )),
+                            column => Line::from(Span::styled(column.value(job), white_style)),
+                        })
+                        .collect::<Vec<Line>>()
+                }
+                JobRow::Step(_, step) => {
+                    let status_style = job_status_map
+                        .get(&step.state.as_str())
+                        .unwrap_or(&red_style);
+                    columns
+                        .iter()
+                        .map(|column| match column {
+                            Column::Id => Line::from(Span::styled(
+                                format!(" └ {}", step.step_id),
+                                purple_style,
+                            )),
+                            Column::State => {
+                                Line::from(Span::styled(step.state.clone(), *status_style))
+                            }
+                            column => {
+                                Line::from(Span::styled(column.step_value(step), white_style))
+                            }
+                        })
+                        .collect::<Vec<Line>>()
+                }
+                JobRow::Array(array) => {
+                    let status_style = job_status_map.get(array.state()).unwrap_or(&red_style);
+                    let first = array.tasks.first();
+                    columns
+                        .iter()
+                        .map(|column| match column {
+                            Column::Id => {
+                                let expander = match app.expanded.contains(&array.array_job_id) {
+                                    true => "▾ ",
+                                    false => "▸ ",
+                                };
+                                let watched = match app.watched.contains(&array.array_job_id) {
+                                    true => " ⚑",
+                                    false => "",
+                                };
+                                Line::from(Span::styled(
+                                    format!(
+                                        "{}{}_[{}]{}",
+                                        expander,
+                                        array.array_job_id,
+                                        array.tasks.len(),
+                                        watched
+                                    ),
+                                    blue_style,
+                                ))
+                            }
+                            Column::State => {
+                                Line::from(Span::styled(array.state().to_string(), *status_style))
+                            }
+                            Column::Name => {
+                                let name = first.map(|t| t.job_name.clone()).unwrap_or_default();
+                                Line::from(Span::styled(
+                                    format!("{} ({})", name, array.state_summary()),
+                                    white_style,
+                                ))
+                            }
+                            // the rest differ from task to task, except for a few
+                            column => {
+                                let value = match (column.shared_by_array(), first) {
+                                    (true, Some(task)) => column.value(task),
+                                    _ => String::new(),
+                                };
+                                Line::from(Span::styled(value, white_style))
+                            }
+                        })
+                        .collect::<Vec<Line>>()
+                }
+            };
+            let changed = match row {
+                JobRow::Job(job) => app.state_just_changed(&job.job_id),
+                JobRow::Array(array) => array
+                    .tasks
+                    .iter()
+                    .any(|t| app.state_just_changed(&t.job_id)),
+                JobRow::Step(..) => false,
+            };
+            acc.push(match changed {
+                true => Row::new(cells).style(changed_style),
+                false => Row::new(cells),
+            });
+            acc
+        });
+    let header = Row::new(columns.iter().map(|column| {
+        let arrow = match app.config.sort {
+            Some(sort) if sort.column == *column && sort.descending => " ▼",
+            Some(sort) if sort.column == *column => " ▲",
+            _ => "",
+        };
+        format!("{}{}", column.title(), arrow)
+    }))
+    .style(blue_style.add_modifier(Modifier::BOLD));
+
+    let (job_style, output_style) = match app.focus {
+        Focus::JobList => (
+            Style::default().fg(Color::Green),
+            Style::default().fg(Color::White),
+        ),
+        Focus::Output => (
+            Style::default().fg(Color::White),
+            Style::default().fg(Color::Green),
+        ),
+    };
+
+    // the count takes the place of the title, the list is narrow enough as it is
+    let title = match app.finished_unseen.len() {
+        0 => "SLURM Job List".to_string(),
+        1 => "1 job finished since last look".to_string(),
+        n => format!("{} jobs finished since last look", n),
+    };
+    let table = Table::new(jobs_as_rows, columns.iter().map(|column| column.width()))
+        .header(header)
+        .block(
+            Block::default()
+                .title(title)
+                .title_alignment(Alignment::Left)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .style(job_style),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::Green)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        );
+
+    match app.array_matrix {
+        Some(_) => render_array_matrix(frame, subchunks[0], app, &job_status_map, job_style),
+        None => frame.render_stateful_widget(table, subchunks[0], &mut app.slurm_jobs.state),
+    }
+
+    match app.right_panel_focus {
+        RightPanelFocus::Output => {
+            let output = text_table("Output", &app.job_output.items, output_style);
+            frame.render_stateful_widget(output, rhs_subchunks[1], &mut app.job_output.state);
+        }
+        RightPanelFocus::JobScript => {
+            let output = text_table("Job Script", &app.job_script.items, output_style);
+            frame.render_stateful_widget(output, rhs_subchunks[1], &mut app.job_script.state);
+        }
+        RightPanelFocus::Efficiency => {
+            render_efficiency(frame, rhs_subchunks[1], selected_job.as_ref(), output_style);
+        }
+        RightPanelFocus::Metrics => {
+            let history = selected_job
+                .as_ref()
+                .and_then(|j| app.job_stats.get(&j.job_id));
+            render_metrics(
+                frame,
+                rhs_subchunks[1],
+                selected_job.as_ref(),
+                history,
+                output_style,
+            );
+        }
+        RightPanelFocus::Dependencies => {
+            render_dependencies(
+                frame,
+                rhs_subchunks[1],
+                app,
+                selected_job.as_ref(),
+                &job_status_map,
+                output_style,
+            );
+        }
+        RightPanelFocus::Nodes => {
+            let nodes = app.get_selected_nodes();
+            render_nodes(
+                frame,
+                rhs_subchunks[1],
+                &nodes,
+                &app.node_details,
+                output_style,
+            );
+        }
+        RightPanelFocus::Hooks => {
+            let output = text_table("Hooks", &app.hook_log.items, output_style);
+            frame.render_stateful_widget(output, rhs_subchunks[1], &mut app.hook_log.state);
+        }
+    };
+}
+
+fn job_status_map() -> HashMap<&'static str, Style> {
+    let light_green_style = Style::default().fg(Color::LightGreen);
+    let red_style = Style::default().fg(Color::LightRed);
+    let orange_style = Style::default().fg(Color::Yellow);
+    HashMap::from([
+        ("F", red_style),
+        ("PD", orange_style),
+        ("R", light_green_style),
+        ("CD", light_green_style),
+        ("CA", orange_style),
+        ("TO", light_green_style),
+        ("PR", orange_style),
+        ("NF", orange_style),
+        ("RV", orange_style),
+        ("S", orange_style),
+        ("OOM", red_style),
+        ("BF", orange_style),
+        ("DL", orange_style),
+    ])
+}
+
+fn text_table<'a>(title: &'a str, lines: &'a [String], style: Style) -> Table<'a> {
+    Table::new(
+        lines.iter().fold(Vec::new(), |mut acc, line| {
+            acc.push(Row::new(vec![Span::styled(
+                line,
+                Style::default().fg(Color::White),
+            )]));
+            acc
+        }),
+        &[Constraint::Percentage(100)],
+    )
+    .block(
+        Block::default()
+            .title(title)
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(style),
+    )
+    .highlight_style(
+        Style::default()
+            .bg(Color::Blue)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+fn efficiency_gauge(title: &str, efficiency: Option<f64>, label: String) -> Gauge<'_> {
+    let colour = match efficiency {
+        Some(e) if e < 0.25 => Color::LightRed,
+        Some(e) if e < 0.6 => Color::Yellow,
+        Some(_) => Color::LightGreen,
+        None => Color::DarkGray,
+    };
+    let label = match efficiency {
+        Some(e) => format!("{:.1}% ({})", e * 100., label),
+        None => "n/a".to_string(),
+    };
+    Gauge::default()
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded),
+        )
+        .gauge_style(Style::default().fg(colour).bg(Color::Black))
+        .ratio(efficiency.unwrap_or(0.).clamp(0., 1.))
+        .label(label)
+}
+
+/// seff style summary of how much of the requested cpu and memory a job actually used.
+fn render_efficiency(frame: &mut Frame, area: Rect, job: Option<&SlurmJob>, style: Style) {
+    let blue_style = Style::default().fg(Color::LightBlue);
+    let white_style = Style::default().fg(Color::White);
+    let block = Block::default()
+        .title("Efficiency")
+        .title_alignment(Alignment::Left)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(style);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let Some(job) = job else {
+        return;
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Length(3),
+            Constraint::Min(0),
+        ])
+        .split(inner);
+
+    let core_walltime = parse_duration(&job.elapsed_time)
+        .zip(job.alloc_cpus.parse::<f64>().ok())
+        .map(|(elapsed, cpus)| format_duration(elapsed * cpus))
+        .unwrap_or_default();
+    let cpu_label = format!(
+        "TotalCPU {} of {} core-walltime",
+        job.total_cpu, core_walltime
+    );
+    frame.render_widget(
+        efficiency_gauge("CPU efficiency", job.cpu_efficiency(), cpu_label),
+        chunks[0],
+    );
+    frame.render_widget(
+        efficiency_gauge(
+            "Memory efficiency",
+            job.mem_efficiency(),
+            job.memory_summary().unwrap_or_default(),
+        ),
+        chunks[1],
+    );
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("ALLOC CPUS  ", blue_style),
+            Span::styled(job.alloc_cpus.to_owned(), white_style),
+        ]),
+        Line::from(vec![
+            Span::styled("REQ TRES    ", blue_style),
+            Span::styled(job.req_tres.to_owned(), white_style),
+        ]),
+        Line::from(vec![
+            Span::styled("ALLOC TRES  ", blue_style),
+            Span::styled(job.alloc_tres.to_owned(), white_style),
+        ]),
+        Line::from(vec![
+            Span::styled("ENERGY      ", blue_style),
+            Span::styled(job.consumed_energy.to_owned(), white_style),
+        ]),
+    ];
+    if !job.is_finished() {
+        lines.push(Line::from(Span::styled(
+            "sacct only updates TotalCPU and MaxRSS as steps finish",
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    // call out jobs that asked for far more memory than they used
+    if let (Some(efficiency), Some(max_rss)) = (job.mem_efficiency(), job.max_rss_bytes()) {
+        if efficiency > 0. && efficiency < 0.5 {
+            lines.push(Line::from(Span::styled(
+                format!(
+                    "Requested {:.1}x the peak memory, --mem={} would be enough",
+                    1. / efficiency,
+                    format_mem(max_rss * 1.25)
+                ),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+    }
+    frame.render_widget(Paragraph::new(lines), chunks[2]);
+}
+
+/// Heatmap of the tasks of an array, one cell per task coloured by state, with the task under
+/// the cursor selected.
+fn render_array_matrix(
+    frame: &mut Frame,
+    area: Rect,
+    app: &mut App,
+    job_status_map: &HashMap<&str, Style>,
+    style: Style,
+) {
+    let Some(matrix) = &app.array_matrix else {
+        return;
+    };
+    let tasks = app.get_matrix_tasks(&matrix.array_job_id);
+    let summary = ArrayJob {
+        array_job_id: matrix.array_job_id.clone(),
+        tasks: tasks.iter().map(|&t| t.clone()).collect(),
+    }
+    .state_summary();
+    let block = Block::default()
+        .title(format!("Array {}: {}", matrix.array_job_id, summary))
+        .title_alignment(Alignment::Left)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(style);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    // each row is labelled with the id of its first task, the rest of the width is cells
+    let label_width = tasks
+        .last()
+        .and_then(|t| t.array_task_id())
+        .unwrap_or(0)
+        .to_string()
+        .len()
+        + 1;
+    let columns = (inner.width as usize).saturating_sub(label_width).max(1);
+    let cursor_row = matrix.cursor / columns;
+    // scroll so that the cursor is always visible
+    let first_row = cursor_row.saturating_sub((inner.height as usize).saturating_sub(1));
+    let lines = tasks
+        .chunks(columns)
+        .enumerate()
+        .skip(first_row)
+        .take(inner.height as usize)
+        .map(|(row, chunk)| {
+            let label = chunk
+                .first()
+                .and_then(|t| t.array_task_id())
+                .map(|id| format!("{:>width$} ", id, width = label_width - 1))
+                .unwrap_or_default();
+            let mut spans = vec![Span::styled(label, Style::default().fg(Color::DarkGray))];
+            spans.extend(chunk.iter().enumerate().map(|(column, task)| {
+                let state_style = *job_status_map
+                    .get(task.state.as_str())
+                    .unwrap_or(&Style::default().fg(Color::LightRed));
+                match row * columns + column == matrix.cursor {
+                    true => Span::styled(
+                        "◆",
+                        Style::default()
+                            .fg(Color::Black)
+                            .bg(state_style.fg.unwrap_or(Color::White)),
+                    ),
+                    false if app.marked.contains(&task.job_id) => Span::styled("▣", state_style),
+                    false => Span::styled("■", state_style),
+                }
+            }));
+            Line::from(spans)
+        })
+        .collect::<Vec<Line>>();
+    frame.render_widget(Paragraph::new(lines), inner);
+    if let Some(matrix) = &mut app.array_matrix {
+        matrix.columns = columns;
+    }
+}
+
+/// Sparklines of the recent sstat history of a running job.
+fn render_metrics(
+    frame: &mut Frame,
+    area: Rect,
+    job: Option<&SlurmJob>,
+    history: Option<&VecDeque<JobStats>>,
+    style: Style,
+) {
+    let block = Block::default()
+        .title("Live metrics")
+        .title_alignment(Alignment::Left)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(style);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    let message = match (job, history) {
+        (Some(job), _) if job.state != "R" => "Live metrics are only available for running jobs",
+        (Some(_), Some(history)) if !history.is_empty() => "",
+        (Some(_), _) => "Waiting for sstat...",
+        (None, _) => "",
+    };
+    let Some(history) = history.filter(|_| message.is_empty()) else {
+        frame.render_widget(
+            Paragraph::new(message).style(Style::default().fg(Color::DarkGray)),
+            inner,
+        );
+        return;
+    };
+
+    // cpu time and disk io are cumulative, so plot the increase between samples instead
+    let deltas = |f: fn(&JobStats) -> f64| sample_deltas(history, f);
+    let values =
+        |f: fn(&JobStats) -> f64| -> Vec<u64> { history.iter().map(|s| f(s) as u64).collect() };
+    let latest = history.back().cloned().unwrap_or_default();
+    let metrics = [
+        (
+            format!(
+                "AveCPU {} (cpu seconds per sample)",
+                format_duration(latest.ave_cpu)
+            ),
+            deltas(|s| s.ave_cpu),
+            Color::LightGreen,
+        ),
+        (
+            format!("AveRSS {}", format_mem(latest.ave_rss)),
+            values(|s| s.ave_rss),
+            Color::LightBlue,
+        ),
+        (
+            format!("MaxRSS {}", format_mem(latest.max_rss)),
+            values(|s| s.max_rss),
+            Color::LightBlue,
+        ),
+        (
+            format!(
+                "MaxDiskRead {} (per sample)",
+                format_mem(latest.max_disk_read)
+            ),
+            deltas(|s| s.max_disk_read),
+            Color::Yellow,
+        ),
+        (
+            format!(
+                "MaxDiskWrite {} (per sample)",
+                format_mem(latest.max_disk_write)
+            ),
+            deltas(|s| s.max_disk_write),
+            Color::Yellow,
+        ),
+        (
+            format!("AveVMSize {}", format_mem(latest.ave_vm_size)),
+            values(|s| s.ave_vm_size),
+            Color::Magenta,
+        ),
+    ];
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            metrics
+                .iter()
+                .map(|_| Constraint::Length(3))
+                .collect::<Vec<_>>(),
+        )
+        .split(inner);
+    for ((title, data, colour), chunk) in metrics.into_iter().zip(chunks.iter()) {
+        // show the most recent samples that fit in the available width
+        let data = &data[data.len().saturating_sub(chunk.width as usize)..];
+        let sparkline = Sparkline::default()
+            .block(Block::default().title(title))
+            .data(data)
+            .style(Style::default().fg(colour));
+        frame.render_widget(sparkline, *chunk);
+    }
+}
+
+fn render_dependencies(
+    frame: &mut Frame,
+    area: Rect,
+    app: &App,
+    selected_job: Option<&SlurmJob>,
+    job_status_map: &HashMap<&str, Style>,
+    style: Style,
+) {
+    let block = Block::default()
+        .title("Dependencies")
+        .title_alignment(Alignment::Left)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(style);
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+    if app.dependency_graph.is_empty() {
+        frame.render_widget(
+            Paragraph::new("No job dependencies").style(Style::default().fg(Color::DarkGray)),
+            inner,
+        );
+        return;
+    }
+
+    let tree = app.dependency_graph.tree();
+    let lines: Vec<Line> =
+        tree.iter()
+            .map(|line| {
+                let job = app.get_job(&line.job_id);
+                // a job that can never start needs cancelling by hand, so make it stand out
+                let never_satisfied = job.is_some_and(|j| j.reason == "DependencyNeverSatisfied");
+                let mut spans = vec![
+                    Span::styled(line.prefix.clone(), Style::default().fg(Color::DarkGray)),
+                    Span::styled(
+                        line.job_id.clone(),
+                        job.and_then(|j| job_status_map.get(j.state.as_str()))
+                            .copied()
+                            .unwrap_or(Style::default().fg(Color::White)),
+                    ),
+                ];
+                if let Some(job) = job {
+                    spans.push(Span::raw(format!(" {} {}", job.job_name, job.state)));
+                }
+                if let Some(dependency) = &line.dependency {
+                    let condition = match &dependency.status {
+                        Some(status) => format!(" [{}({})]", dependency.kind, status),
+                        None => format!(" [{}]", dependency.kind),
+                    };
+                    spans.push(Span::styled(condition, Style::default().fg(Color::Magenta)));
+                }
+                if never_satisfied {
+                    spans.push(Span::styled(
+                        " DependencyNeverSatisfied",
+                        Style::default()
+                            .fg(Color::LightRed)
+                            .add_modifier(Modifier::BOLD),
+                    ));
+                }
+                let selected = selected_job.is_some_and(|j| j.job_id == line.job_id);
+                match selected {
+                    true => Line::from(spans)
+                        .patch_style(Style::default().add_modifier(Modifier::REVERSED)),
+                    false => Line::from(spans),
+                }
+            })
+            .collect();
+
+    // keep the selected job in view
+    let selected = selected_job
+        .and_then(|j| tree.iter().position(|line| line.job_id == j.job_id))
+        .unwrap_or(0);
+    let offset = selected.saturating_sub(inner.height as usize / 2) as u16;
+    frame.render_widget(Paragraph::new(lines).scroll((offset, 0)), inner);
+}
+
+/// Node counts and free resources per partition, with the nodes of the selected one below.
+fn render_cluster(app: &mut App, frame: &mut Frame, area: Rect) {
+    let blue_style = Style::default().fg(Color::LightBlue);
+    let light_green_style = Style::default().fg(Color::LightGreen);
+    let red_style = Style::default().fg(Color::LightRed);
+    let orange_style = Style::default().fg(Color::Yellow);
+    let white_style = Style::default().fg(Color::White);
+    let highlight_style = Style::default()
+        .bg(Color::Green)
+        .fg(Color::Black)
+        .add_modifier(Modifier::BOLD);
+    let (partition_style, node_style) = match app.cluster.drill_down {
+        false => (
+            Style::default().fg(Color::Green),
+            Style::default().fg(Color::White),
+        ),
+        true => (
+            Style::default().fg(Color::White),
+            Style::default().fg(Color::Green),
+        ),
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(app.cluster.partitions.len() as u16 + 3),
+            Constraint::Min(10),
+        ])
+        .split(area);
+
+    let count = |n: usize, style: Style| match n {
+        0 => Span::styled("0", Style::default().fg(Color::DarkGray)),
+        n => Span::styled(n.to_string(), style),
+    };
+    let partitions = app
+        .cluster
+        .partitions
+        .items
+        .iter()
+        .map(|p| {
+            let name = match p.default {
+                true => format!("{}*", p.name),
+                false => p.name.clone(),
+            };
+            Row::new(vec![
+                Span::styled(name, blue_style),
+                count(p.idle, light_green_style),
+                count(p.mixed, orange_style),
+                count(p.alloc, white_style),
+                count(p.down, red_style),
+                Span::styled(format!("{}/{}", p.cpus_idle, p.cpus_total), white_style),
+                Span::styled(p.gres_summary(), white_style),
+                Span::styled(p.time_limit.clone(), white_style),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let header = Row::new(vec![
+        "PARTITION",
+        "IDLE",
+        "MIXED",
+        "ALLOC",
+        "DOWN",
+        "CPUS FREE",
+        "GRES FREE",
+        "TIME LIMIT",
+    ])
+    .style(blue_style.add_modifier(Modifier::BOLD));
+    let partitions = Table::new(
+        partitions,
+        &[
+            Constraint::Length(16),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(12),
+            Constraint::Min(20),
+            Constraint::Length(12),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title("Partitions")
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(partition_style),
+    )
+    .highlight_style(highlight_style);
+    frame.render_stateful_widget(partitions, chunks[0], &mut app.cluster.partitions.state);
+
+    let nodes = app
+        .cluster
+        .nodes
+        .items
+        .iter()
+        .map(|n| {
+            let state_style = match n.node_state() {
+                NodeState::Idle => light_green_style,
+                NodeState::Mixed => orange_style,
+                NodeState::Alloc => white_style,
+                NodeState::Down => red_style,
+                NodeState::Other => orange_style,
+            };
+            Row::new(vec![
+                Span::styled(n.name.clone(), blue_style),
+                Span::styled(n.state.clone(), state_style),
+                Span::styled(
+                    format!(
+                        "{}/{}/{}/{}",
+                        n.cpus_alloc, n.cpus_idle, n.cpus_other, n.cpus_total
+                    ),
+                    white_style,
+                ),
+                Span::styled(n.gres_used.clone(), white_style),
+                Span::styled(n.reason.clone(), red_style),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let header = Row::new(vec!["NODE", "STATE", "CPUS A/I/O/T", "GRES USED", "REASON"])
+        .style(blue_style.add_modifier(Modifier::BOLD));
+    let title = match app.cluster.partitions.selected() {
+        Some(p) => format!("Nodes in {}", p.name),
+        None => "Nodes".to_string(),
+    };
+    let nodes = Table::new(
+        nodes,
+        &[
+            Constraint::Length(16),
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Percentage(30),
+            Constraint::Percentage(70),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title(title)
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(node_style),
+    )
+    .highlight_style(highlight_style);
+    frame.render_stateful_widget(nodes, chunks[1], &mut app.cluster.nodes.state);
+}
+
+/// scontrol's view of each node the selected job is allocated to.
+fn render_nodes(
+    frame: &mut Frame,
+    area: Rect,
+    nodes: &[String],
+    details: &[NodeDetail],
+    style: Style,
+) {
+    let white_style = Style::default().fg(Color::White);
+    let blue_style = Style::default().fg(Color::LightBlue);
+    let orange_style = Style::default().fg(Color::Yellow);
+    let red_style = Style::default().fg(Color::LightRed);
+    let block = Block::default()
+        .title("Nodes")
+        .title_alignment(Alignment::Left)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .style(style);
+    if nodes.is_empty() {
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        frame.render_widget(
+            Paragraph::new("No nodes allocated").style(Style::default().fg(Color::DarkGray)),
+            inner,
+        );
+        return;
+    }
+
+    let rows = nodes
+        .iter()
+        .map(|name| {
+            let Some(node) = details.iter().find(|n| &n.name == name) else {
+                return Row::new(vec![
+                    Span::styled(name.clone(), blue_style),
+                    Span::styled("waiting...", Style::default().fg(Color::DarkGray)),
+                ]);
+            };
+            let state_style = match node.is_unhealthy() {
+                true => red_style.add_modifier(Modifier::BOLD),
+                false => white_style,
+            };
+            // more runnable threads than cores means the job is competing for cpu
+            let load_style = match node.cpu_load {
+                Some(load) if load > node.cpus_total as f64 * 1.1 => orange_style,
+                _ => white_style,
+            };
+            let load = match node.cpu_load {
+                Some(load) => format!("{:.1}/{}", load, node.cpus_total),
+                None => format!("-/{}", node.cpus_total),
+            };
+            let mem_style = match (node.free_memory, node.real_memory) {
+                (Some(free), Some(real)) if free < real * 0.05 => orange_style,
+                _ => white_style,
+            };
+            let mem = |bytes: Option<f64>| bytes.map(format_mem).unwrap_or("-".to_string());
+            let gres = match (node.gres_used.is_empty(), node.gres.is_empty()) {
+                (_, true) => String::new(),
+                (true, false) => node.gres.clone(),
+                (false, false) => format!("{} of {}", node.gres_used, node.gres),
+            };
+            Row::new(vec![
+                Span::styled(node.name.clone(), blue_style),
+                Span::styled(node.state.clone(), state_style),
+                Span::styled(load, load_style),
+                Span::styled(
+                    format!("{}/{}", node.cpus_alloc, node.cpus_total),
+                    white_style,
+                ),
+                Span::styled(
+                    format!("{} of {}", mem(node.free_memory), mem(node.real_memory)),
+                    mem_style,
+                ),
+                Span::styled(gres, white_style),
+                Span::styled(node.reason.clone(), red_style),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let header = Row::new(vec![
+        "NODE", "STATE", "LOAD", "CPUS", "MEM FREE", "GRES", "REASON",
+    ])
+    .style(blue_style.add_modifier(Modifier::BOLD));
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Length(14),
+            Constraint::Length(14),
+            Constraint::Length(10),
+            Constraint::Length(9),
+            Constraint::Length(16),
+            Constraint::Percentage(40),
+            Constraint::Percentage(60),
+        ],
+    )
+    .header(header)
+    .block(block);
+    frame.render_widget(table, area);
+}
+
+/// Nodes ranked by how often the user's jobs failed on them, with the jobs that ran on the
+/// selected node and an --exclude option for the flagged ones.
+fn render_node_failures(app: &mut App, frame: &mut Frame, area: Rect) {
+    let blue_style = Style::default().fg(Color::LightBlue);
+    let white_style = Style::default().fg(Color::White);
+    let red_style = Style::default().fg(Color::LightRed);
+    let job_status_map = job_status_map();
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area);
+    let rhs_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Min(10)])
+        .split(chunks[1]);
+
+    let rows = app
+        .node_failures
+        .items
+        .iter()
+        .map(|n| {
+            let style = match n.abnormal {
+                true => red_style.add_modifier(Modifier::BOLD),
+                false => white_style,
+            };
+            Row::new(vec![
+                Span::styled(
+                    format!("{}{}", n.node, if n.abnormal { " !" } else { "" }),
+                    match n.abnormal {
+                        true => style,
+                        false => blue_style,
+                    },
+                ),
+                Span::styled(n.jobs.to_string(), white_style),
+                Span::styled(n.failed.to_string(), white_style),
+                Span::styled(n.node_fail.to_string(), white_style),
+                Span::styled(format!("{:.0}%", n.failure_rate() * 100.), style),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let header = Row::new(vec!["NODE", "JOBS", "F", "NF", "FAIL RATE"])
+        .style(blue_style.add_modifier(Modifier::BOLD));
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Percentage(40),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(6),
+            Constraint::Length(10),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title(format!(
+                "Node failures over {} finished jobs seen since swatch started, not kept between runs",
+                app.finished_jobs.len()
+            ))
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(Style::default().fg(Color::Green)),
+    )
+    .highlight_style(
+        Style::default()
+            .bg(Color::Green)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    );
+    frame.render_stateful_widget(table, chunks[0], &mut app.node_failures.state);
+
+    let exclude = match exclude_option(&app.node_failures.items) {
+        Some(exclude) => Span::styled(exclude, red_style),
+        None => Span::styled(
+            "No nodes with abnormal failure counts",
+            Style::default().fg(Color::DarkGray),
+        ),
+    };
+    let exclude = Paragraph::new(exclude).block(
+        Block::default()
+            .title("Exclude")
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    frame.render_widget(exclude, rhs_chunks[0]);
+
+    let node = app.node_failures.selected().map(|n| n.node.clone());
+    let jobs = node
+        .as_ref()
+        .map(|node| app.get_node_jobs(node))
+        .unwrap_or_default();
+    let rows = jobs
+        .iter()
+        .map(|job| {
+            Row::new(vec![
+                Span::styled(job.job_id.clone(), blue_style),
+                Span::styled(
+                    job.state.clone(),
+                    *job_status_map.get(job.state.as_str()).unwrap_or(&red_style),
+                ),
+                Span::styled(job.end.clone(), white_style),
+                Span::styled(job.job_name.clone(), white_style),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let title = match &node {
+        Some(node) => format!("Jobs on {}", node),
+        None => "Jobs".to_string(),
+    };
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Length(14),
+            Constraint::Length(4),
+            Constraint::Length(20),
+            Constraint::Min(10),
+        ],
+    )
+    .block(
+        Block::default()
+            .title(title)
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded),
+    );
+    frame.render_widget(table, rhs_chunks[1]);
+}
+
+/// Fairshare, decayed usage and this month's cpu hours of each of the user's accounts.
+fn render_accounts(app: &mut App, frame: &mut Frame, area: Rect) {
+    let blue_style = Style::default().fg(Color::LightBlue);
+    let light_green_style = Style::default().fg(Color::LightGreen);
+    let orange_style = Style::default().fg(Color::Yellow);
+    let red_style = Style::default().fg(Color::LightRed);
+    let white_style = Style::default().fg(Color::White);
+
+    if app.accounts.is_empty() {
+        let block = Block::default()
+            .title("Accounts")
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        frame.render_widget(
+            Paragraph::new("Waiting for sshare and sreport...")
+                .style(Style::default().fg(Color::DarkGray)),
+            inner,
+        );
+        return;
+    }
+
+    let optional = |value: Option<f64>| match value {
+        Some(value) => format!("{:.3}", value),
+        None => "-".to_string(),
+    };
+    let rows = app
+        .accounts
+        .items
+        .iter()
+        .map(|account| {
+            // a low fairshare factor means the account's jobs will queue behind others
+            let fairshare_style = match account.fairshare {
+                Some(f) if f < 0.2 => red_style,
+                Some(f) if f < 0.5 => orange_style,
+                _ => light_green_style,
+            };
+            let (budget, budget_style) =
+                match (account.remaining_hours(), account.cpu_minutes_limit) {
+                    (Some(remaining), Some(limit)) => (
+                        format!(
+                            "{} of {}",
+                            format_count(remaining),
+                            format_count(limit / 60.)
+                        ),
+                        match remaining / (limit / 60.) {
+                            r if r < 0.1 => red_style,
+                            r if r < 0.3 => orange_style,
+                            _ => light_green_style,
+                        },
+                    ),
+                    _ => ("no limit".to_string(), white_style),
+                };
+            Row::new(vec![
+                Span::styled(account.account.clone(), blue_style),
+                Span::styled(optional(account.fairshare), fairshare_style),
+                Span::styled(optional(account.norm_shares), white_style),
+                Span::styled(optional(account.effective_usage), white_style),
+                Span::styled(
+                    format!(
+                        "{} / {}",
+                        format_count(account.raw_usage),
+                        format_count(account.account_raw_usage)
+                    ),
+                    white_style,
+                ),
+                Span::styled(
+                    format!(
+                        "{} / {}",
+                        format_count(account.user_month_hours),
+                        format_count(account.month_hours)
+                    ),
+                    white_style,
+                ),
+                Span::styled(budget, budget_style),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let header = Row::new(vec![
+        "ACCOUNT",
+        "FAIRSHARE",
+        "NORM SHARES",
+        "EFF USAGE",
+        "RAW USAGE YOU/ACCT",
+        "CPU-H THIS MONTH YOU/ACCT",
+        "CPU-H LEFT",
+    ])
+    .style(blue_style.add_modifier(Modifier::BOLD));
+    let table = Table::new(
+        rows,
+        &[
+            Constraint::Length(16),
+            Constraint::Length(10),
+            Constraint::Length(12),
+            Constraint::Length(10),
+            Constraint::Length(20),
+            Constraint::Length(26),
+            Constraint::Min(16),
+        ],
+    )
+    .header(header)
+    .block(
+        Block::default()
+            .title("Accounts")
+            .title_alignment(Alignment::Left)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(Style::default().fg(Color::Green)),
+    )
+    .highlight_style(
+        Style::default()
+            .bg(Color::Green)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    );
+    frame.render_stateful_widget(table, area, &mut app.accounts.state);
+}
+
+// how long ago the output was written, in whole minutes
+fn format_age(age: Duration) -> String {
+    match age.as_secs() / 60 {
+        0 => "<1 min ago".to_string(),
+        minutes => format!("{} min ago", minutes),
+    }
+}
+
+// shorten large counts, e.g. 1234567 to "1.2M"
+fn format_count(value: f64) -> String {
+    match value {
+        v if v >= 1e9 => format!("{:.1}G", v / 1e9),
+        v if v >= 1e6 => format!("{:.1}M", v / 1e6),
+        v if v >= 1e4 => format!("{:.1}k", v / 1e3),
+        v => format!("{:.0}", v),
+    }
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -0,0 +1,653 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::Write,
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{DateTime, Local};
+use crossbeam::{
+    channel::{tick, unbounded, Receiver, RecvError, Sender},
+    select,
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::app::AppMessage;
+use crate::file_watcher::Tail;
+use crate::query::glob_match;
+use crate::slurm::{seconds_until, SlurmJob};
+
+// how far back into its output to look for a job that started running while swatch watched
+const INITIAL_TAIL_BYTES: u64 = 64 * 1024;
+// a job that has only just gone quiet isn't stalled however short a time it has run for
+const MIN_STALL: Duration = Duration::from_secs(5 * 60);
+
+fn default_signal() -> String {
+    "USR1".to_string()
+}
+
+/// What a rule does to the job when it fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchdogAction {
+    Cancel,
+    Alert,
+    Signal,
+}
+
+/// A check on the output of running jobs, a `[[watchdog.rules]]` table in the config, e.g.
+///
+/// ```toml
+/// [[watchdog.rules]]
+/// job_name = "train_*"
+/// match = "loss = nan"
+/// action = "cancel"
+///
+/// [[watchdog.rules]]
+/// stall_minutes = 30
+/// action = "signal"
+/// signal = "USR1"
+/// ```
+///
+/// A match rule only looks at output written after swatch started, so restarting swatch doesn't
+/// fire it again for a line it has already acted on.  Lines a job wrote while swatch wasn't
+/// running are never matched.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WatchdogRule {
+    // a job id, or an array's for all of its tasks
+    #[serde(default)]
+    pub job_id: Option<String>,
+    // a pattern the job name has to match, e.g. "train_*"
+    #[serde(default)]
+    pub job_name: Option<String>,
+    // text to look for in the output, a regular expression if `regex` is set
+    #[serde(default, rename = "match")]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub regex: bool,
+    // minutes without new output while the job runs
+    #[serde(default)]
+    pub stall_minutes: Option<u64>,
+    pub action: WatchdogAction,
+    // sent by the signal action, e.g. "USR1" or "TERM"
+    #[serde(default = "default_signal")]
+    pub signal: String,
+}
+
+impl WatchdogRule {
+    pub fn applies(&self, job_id: &str, job_name: &str) -> bool {
+        let array_job_id = job_id.split_once('_').map(|(array, _)| array);
+        self.job_id
+            .as_ref()
+            .is_none_or(|id| id == job_id || Some(id.as_str()) == array_job_id)
+            && self
+                .job_name
+                .as_ref()
+                .is_none_or(|pattern| glob_match(pattern, job_name))
+    }
+
+    /// Whether the rule makes sense, checked when the config is loaded.
+    pub fn check(&self) -> Result<(), String> {
+        if self.pattern.is_some() == self.stall_minutes.is_some() {
+            return Err("a watchdog rule needs either a match or stall_minutes".to_string());
+        }
+        if let (Some(pattern), true) = (&self.pattern, self.regex) {
+            Regex::new(pattern).map_err(|e| format!("watchdog rule {}: {}", pattern, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// The `[watchdog]` section of the config.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    // log what the rules would do without doing it
+    pub dry_run: bool,
+    pub rules: Vec<WatchdogRule>,
+}
+
+/// The `[stall]` section of the config, when a running job's quiet output counts as stalled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StallConfig {
+    // quiet for more than this fraction of the job's elapsed time, 0 to turn it off
+    pub fraction: f64,
+    // quiet for more than this many minutes, 0 to turn it off
+    pub minutes: u64,
+}
+
+impl Default for StallConfig {
+    fn default() -> Self {
+        Self {
+            fraction: 0.5,
+            minutes: 60,
+        }
+    }
+}
+
+impl StallConfig {
+    /// Whether a job that has written nothing for `quiet` after running for `elapsed` seconds is
+    /// stalled.
+    pub fn is_stalled(&self, quiet: Duration, elapsed: Option<f64>) -> bool {
+        if quiet < MIN_STALL {
+            return false;
+        }
+        let too_long = self.minutes > 0 && quiet > Duration::from_secs(self.minutes * 60);
+        let too_much = self.fraction > 0.
+            && elapsed.is_some_and(|elapsed| quiet.as_secs_f64() > elapsed * self.fraction);
+        too_long || too_much
+    }
+}
+
+/// A rule firing for a job.
+#[derive(Debug, Clone)]
+pub struct WatchdogEvent {
+    pub job_id: String,
+    pub job_name: String,
+    pub action: WatchdogAction,
+    pub signal: String,
+    // what the rule saw, e.g. the matching line
+    pub reason: String,
+}
+
+impl fmt::Display for WatchdogEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match self.action {
+            WatchdogAction::Cancel => "cancel".to_string(),
+            WatchdogAction::Alert => "alert".to_string(),
+            WatchdogAction::Signal => format!("signal {}", self.signal),
+        };
+        write!(
+            f,
+            "{} {} ({}): {}",
+            action, self.job_id, self.job_name, self.reason
+        )
+    }
+}
+
+/// Where the actions the watchdog took are logged, under $XDG_STATE_HOME if it is set.
+pub fn log_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(base.join("swatch").join("watchdog.log"))
+}
+
+pub fn log(event: &WatchdogEvent, dry_run: bool) -> std::io::Result<()> {
+    let path = log_path().ok_or_else(|| std::io::Error::other("HOME is not set"))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", log_line(event, dry_run, Local::now()))
+}
+
+fn log_line(event: &WatchdogEvent, dry_run: bool, time: DateTime<Local>) -> String {
+    let dry_run = match dry_run {
+        true => " (dry run)",
+        false => "",
+    };
+    format!("{}{} {}", time.format("%Y-%m-%d %H:%M:%S"), dry_run, event)
+}
+
+/// A running job the watchdog reads the output of.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedJob {
+    pub job_id: String,
+    pub job_name: String,
+    pub stdout: PathBuf,
+    pub stderr: PathBuf,
+    // when it started running, e.g. "2024-03-01T12:00:00"
+    pub start: String,
+}
+
+impl WatchedJob {
+    pub fn new(job: &SlurmJob) -> Self {
+        Self {
+            job_id: job.job_id.clone(),
+            job_name: job.job_name.clone(),
+            stdout: job.stdout_path(),
+            stderr: job.stderr_path(),
+            start: job.start.clone(),
+        }
+    }
+
+    fn paths(&self) -> Vec<PathBuf> {
+        match self.stdout == self.stderr {
+            true => vec![self.stdout.clone()],
+            false => vec![self.stdout.clone(), self.stderr.clone()],
+        }
+    }
+}
+
+enum Matcher {
+    Text(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn matches(&self, line: &str) -> bool {
+        match self {
+            Matcher::Text(text) => line.contains(text.as_str()),
+            Matcher::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+// what the watchdog keeps for each job between reads
+struct JobState {
+    job: WatchedJob,
+    // each output file, with the end of it that isn't a whole line yet
+    tails: Vec<(Tail, String)>,
+    // rules that have fired, a match fires once, a stall again once output has resumed
+    fired: HashSet<usize>,
+}
+
+enum WatchdogMessage {
+    Jobs(Vec<WatchedJob>),
+}
+
+struct Watchdog {
+    app: Sender<AppMessage>,
+    receiver: Receiver<WatchdogMessage>,
+    interval: Duration,
+    rules: Vec<(WatchdogRule, Option<Matcher>)>,
+    jobs: HashMap<String, JobState>,
+    attached: Instant,
+}
+
+#[derive(Debug)]
+pub struct WatchdogHandle {
+    sender: Sender<WatchdogMessage>,
+    jobs: Vec<WatchedJob>,
+}
+
+impl Watchdog {
+    fn new(
+        app: Sender<AppMessage>,
+        receiver: Receiver<WatchdogMessage>,
+        interval: Duration,
+        rules: Vec<WatchdogRule>,
+    ) -> Self {
+        // the config is checked when it is loaded, a rule that still doesn't hold up is left out
+        // rather than kept with a match that never fires
+        let rules = rules
+            .into_iter()
+            .filter(|rule| rule.check().is_ok())
+            .filter_map(|rule| {
+                let matcher = match (&rule.pattern, rule.regex) {
+                    (Some(pattern), true) => Some(Matcher::Regex(Regex::new(pattern).ok()?)),
+                    (Some(pattern), false) => Some(Matcher::Text(pattern.clone())),
+                    (None, _) => None,
+                };
+                Some((rule, matcher))
+            })
+            .collect();
+        Self {
+            app,
+            receiver,
+            interval,
+            rules,
+            jobs: HashMap::new(),
+            attached: Instant::now(),
+        }
+    }
+
+    fn run(&mut self) -> Result<(), RecvError> {
+        let ticker = tick(self.interval);
+        loop {
+            select! {
+                recv(self.receiver) -> msg => {
+                    match msg? {
+                        WatchdogMessage::Jobs(jobs) => self.set_jobs(jobs),
+                    }
+                }
+                recv(ticker) -> _ => self.check(),
+            }
+        }
+    }
+
+    fn set_jobs(&mut self, jobs: Vec<WatchedJob>) {
+        self.jobs
+            .retain(|id, state| jobs.iter().any(|j| j.job_id == *id && j == &state.job));
+        for job in jobs {
+            // only the output a match rule looks at is read, the rest is only checked for changes
+            let read = self.rules.iter().any(|(rule, matcher)| {
+                matcher.is_some() && rule.applies(&job.job_id, &job.job_name)
+            });
+            // output from before swatch started isn't matched, a job that has started since has
+            // written all of its output while being watched
+            let started_since = seconds_until(&job.start)
+                .is_some_and(|until| -until <= self.attached.elapsed().as_secs() as i64);
+            let look_back = match started_since {
+                true => INITIAL_TAIL_BYTES,
+                false => 0,
+            };
+            self.jobs
+                .entry(job.job_id.clone())
+                .or_insert_with(|| JobState {
+                    tails: match read {
+                        true => job
+                            .paths()
+                            .into_iter()
+                            .map(|path| (Tail::from_end(path, look_back), String::new()))
+                            .collect(),
+                        false => Vec::new(),
+                    },
+                    job,
+                    fired: HashSet::new(),
+                });
+        }
+        self.check();
+    }
+
+    fn check(&mut self) {
+        let mut events = Vec::new();
+        let mut last_outputs = HashMap::new();
+        for state in self.jobs.values_mut() {
+            let mut new = String::new();
+            for (tail, partial) in &mut state.tails {
+                let (text, truncated) = tail.read().unwrap_or_default();
+                // a line held back from before the file was truncated won't be finished
+                if truncated {
+                    partial.clear();
+                }
+                partial.push_str(&text);
+                // hold back a line that is still being written
+                let complete = partial.rfind('\n').map_or(0, |i| i + 1);
+                let rest = partial.split_off(complete);
+                new.push_str(&std::mem::replace(partial, rest));
+            }
+            let last_output = state
+                .job
+                .paths()
+                .iter()
+                .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+                .max();
+            if let Some(modified) = last_output {
+                last_outputs.insert(state.job.job_id.clone(), modified);
+            }
+
+            for (index, (rule, matcher)) in self.rules.iter().enumerate() {
+                if !rule.applies(&state.job.job_id, &state.job.job_name) {
+                    continue;
+                }
+                let reason = match (matcher, rule.stall_minutes) {
+                    (Some(matcher), _) if !state.fired.contains(&index) => new
+                        .lines()
+                        .find(|line| matcher.matches(line))
+                        .map(|line| format!("output matched: {}", line.trim())),
+                    (None, Some(minutes)) => {
+                        let quiet = last_output
+                            .and_then(|t| SystemTime::now().duration_since(t).ok())
+                            .unwrap_or_default();
+                        match quiet >= Duration::from_secs(minutes * 60) {
+                            true if !state.fired.contains(&index) => {
+                                Some(format!("no output for {} minutes", quiet.as_secs() / 60))
+                            }
+                            true => None,
+                            false => {
+                                state.fired.remove(&index);
+                                None
+                            }
+                        }
+                    }
+                    _ => None,
+                };
+                if let Some(reason) = reason {
+                    state.fired.insert(index);
+                    events.push(WatchdogEvent {
+                        job_id: state.job.job_id.clone(),
+                        job_name: state.job.job_name.clone(),
+                        action: rule.action,
+                        signal: rule.signal.clone(),
+                        reason,
+                    });
+                }
+            }
+        }
+        for event in events {
+            self.app.send(AppMessage::WatchdogFired(event)).unwrap();
+        }
+        self.app.send(AppMessage::LastOutput(last_outputs)).unwrap();
+    }
+}
+
+impl WatchdogHandle {
+    pub fn new(app: Sender<AppMessage>, interval: Duration, rules: Vec<WatchdogRule>) -> Self {
+        let (sender, receiver) = unbounded();
+        let mut actor = Watchdog::new(app, receiver, interval, rules);
+        thread::spawn(move || actor.run());
+        Self {
+            sender,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Set the running jobs, whose output times feed the stall indicator as well as the rules.
+    pub fn set_jobs(&mut self, jobs: Vec<WatchedJob>) {
+        if self.jobs != jobs {
+            self.jobs = jobs.clone();
+            self.sender.send(WatchdogMessage::Jobs(jobs)).unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn rule(job_id: Option<&str>, job_name: Option<&str>) -> WatchdogRule {
+        WatchdogRule {
+            job_id: job_id.map(|id| id.to_string()),
+            job_name: job_name.map(|name| name.to_string()),
+            pattern: Some("loss = nan".to_string()),
+            regex: false,
+            stall_minutes: None,
+            action: WatchdogAction::Cancel,
+            signal: default_signal(),
+        }
+    }
+
+    #[test]
+    fn applies() {
+        assert!(rule(None, None).applies("12", "anything"));
+        let array = rule(Some("12"), None);
+        assert!(array.applies("12", "a"));
+        assert!(array.applies("12_3", "a"));
+        assert!(!array.applies("123", "a"));
+        assert!(!array.applies("123_1", "a"));
+        assert!(rule(Some("12_3"), None).applies("12_3", "a"));
+        assert!(!rule(Some("12_3"), None).applies("12_4", "a"));
+        let named = rule(Some("12"), Some("train_*"));
+        assert!(named.applies("12_1", "train_big"));
+        assert!(!named.applies("12_1", "eval"));
+    }
+
+    #[test]
+    fn rules_need_a_match_or_a_stall() {
+        assert!(rule(None, None).check().is_ok());
+        let both = WatchdogRule {
+            stall_minutes: Some(5),
+            ..rule(None, None)
+        };
+        assert!(both.check().is_err());
+        let neither = WatchdogRule {
+            pattern: None,
+            ..rule(None, None)
+        };
+        assert!(neither.check().is_err());
+        let bad_regex = WatchdogRule {
+            pattern: Some("(".to_string()),
+            regex: true,
+            ..rule(None, None)
+        };
+        assert!(bad_regex.check().is_err());
+    }
+
+    #[test]
+    fn stalls() {
+        let config = StallConfig::default();
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        // never stalled before MIN_STALL, however short the job
+        assert!(!config.is_stalled(minutes(4), Some(60.)));
+        assert!(config.is_stalled(minutes(6), Some(600.)));
+        assert!(!config.is_stalled(minutes(6), Some(3600.)));
+        assert!(config.is_stalled(minutes(61), None));
+        let off = StallConfig {
+            fraction: 0.,
+            minutes: 0,
+        };
+        assert!(!off.is_stalled(minutes(600), Some(60.)));
+    }
+
+    #[test]
+    fn log_lines() {
+        let event = WatchdogEvent {
+            job_id: "12_3".to_string(),
+            job_name: "train".to_string(),
+            action: WatchdogAction::Signal,
+            signal: "USR1".to_string(),
+            reason: "no output for 30 minutes".to_string(),
+        };
+        assert_eq!(
+            event.to_string(),
+            "signal USR1 12_3 (train): no output for 30 minutes"
+        );
+        let time = Local.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        assert_eq!(
+            log_line(&event, true, time),
+            "2024-03-01 12:00:00 (dry run) signal USR1 12_3 (train): no output for 30 minutes"
+        );
+        assert!(log_line(&event, false, time).starts_with("2024-03-01 12:00:00 signal"));
+        let config: WatchdogConfig = toml::from_str("dry_run = true\n").unwrap();
+        assert!(config.dry_run && config.rules.is_empty());
+    }
+
+    fn output(name: &str, contents: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("swatch-watchdog-{}-{}", std::process::id(), name));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn append(path: &PathBuf, text: &str) {
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        write!(file, "{}", text).unwrap();
+    }
+
+    fn watched(job_id: &str, path: &std::path::Path, start: &str) -> WatchedJob {
+        WatchedJob {
+            job_id: job_id.to_string(),
+            job_name: "train".to_string(),
+            stdout: path.to_path_buf(),
+            stderr: path.to_path_buf(),
+            start: start.to_string(),
+        }
+    }
+
+    fn fired(receiver: &Receiver<AppMessage>) -> Vec<String> {
+        receiver
+            .try_iter()
+            .filter_map(|message| match message {
+                AppMessage::WatchdogFired(event) => Some(event.to_string()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn matches_only_new_output() {
+        let (app, receiver) = unbounded();
+        let regex = WatchdogRule {
+            pattern: Some(r"step \d+ failed".to_string()),
+            regex: true,
+            action: WatchdogAction::Alert,
+            ..rule(None, None)
+        };
+        let mut watchdog = Watchdog::new(
+            app,
+            unbounded().1,
+            Duration::from_secs(1),
+            vec![rule(None, None), regex],
+        );
+        let old = output("old", "loss = nan\nstep 1 failed\n");
+        let new = output("new", "step 2 failed\n");
+        let now = Local::now().format("%Y-%m-%dT%H:%M:%S").to_string();
+        watchdog.set_jobs(vec![
+            watched("1", &old, "2020-01-01T00:00:00"),
+            watched("2", &new, &now),
+        ]);
+        // what the job running before the watchdog started wrote is left alone, all of the
+        // output of the one that started since is looked at
+        assert_eq!(
+            fired(&receiver),
+            ["alert 2 (train): output matched: step 2 failed"]
+        );
+
+        // a line still being written isn't matched until it is finished
+        append(&old, "epoch 2 loss = n");
+        watchdog.check();
+        assert!(fired(&receiver).is_empty());
+        append(&old, "an\n");
+        watchdog.check();
+        assert_eq!(
+            fired(&receiver),
+            ["cancel 1 (train): output matched: epoch 2 loss = nan"]
+        );
+        // a match fires once
+        append(&old, "loss = nan\n");
+        watchdog.check();
+        assert!(fired(&receiver).is_empty());
+        std::fs::remove_file(&old).unwrap();
+        std::fs::remove_file(&new).unwrap();
+    }
+
+    #[test]
+    fn stall_rules_fire_again_after_output_resumes() {
+        let (app, receiver) = unbounded();
+        let stall = WatchdogRule {
+            pattern: None,
+            stall_minutes: Some(30),
+            action: WatchdogAction::Alert,
+            ..rule(None, None)
+        };
+        let mut watchdog = Watchdog::new(app, unbounded().1, Duration::from_secs(1), vec![stall]);
+        let path = output("stall", "");
+        let quiet_since = |minutes: u64| {
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(SystemTime::now() - Duration::from_secs(minutes * 60))
+                .unwrap()
+        };
+        quiet_since(45);
+        watchdog.set_jobs(vec![watched("1", &path, "2020-01-01T00:00:00")]);
+        assert_eq!(
+            fired(&receiver),
+            ["alert 1 (train): no output for 45 minutes"]
+        );
+        watchdog.check();
+        assert!(fired(&receiver).is_empty());
+        quiet_since(1);
+        watchdog.check();
+        assert!(fired(&receiver).is_empty());
+        quiet_since(31);
+        watchdog.check();
+        assert_eq!(
+            fired(&receiver),
+            ["alert 1 (train): no output for 31 minutes"]
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}